@@ -0,0 +1,58 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use log::info;
+use npc_generator_core::Units;
+use serde::{Deserialize, Serialize};
+
+use crate::export::ExportFormat;
+
+/// User preferences, read from `config.ron` in the config directory.
+/// Command line arguments override every setting.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    /// Data pack directory or zip file used when no `--pack` is given
+    pub data: Option<PathBuf>,
+    /// Name of the weight preset to use
+    pub preset: Option<String>,
+    /// Output format of generate mode and the format shown in the GUI
+    pub format: ExportFormat,
+    /// Whether generated NPCs get flavor text. Without a value they get it,
+    /// except in statistics mode where it would only slow down the sampling.
+    pub flavor: Option<bool>,
+    /// Generate NPCs from a random archetype of this level
+    pub level: Option<i8>,
+    /// Units of the heights and weights in flavor text
+    pub units: Units,
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|mut path| {
+        path.push("pf2e_npc_generator");
+        path.push("config.ron");
+        path
+    })
+}
+
+impl Config {
+    /// Reads the config file, a missing file is the default config.
+    pub fn load() -> Result<Self> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+        if !path.is_file() {
+            info!("No config file at {path:?}, using the defaults");
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&path).with_context(|| format!("Can't read {path:?}"))?;
+        ron::from_str(&text).with_context(|| format!("Can't parse {path:?}"))
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default().struct_names(true),
+        )?)
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use npc_generator_core::Statblock;
+use serde::{Deserialize, Serialize};
+
+mod pdf;
+pub use pdf::write_pdf_cards;
+mod template;
+pub use template::{find_user_template, load_user_templates, UserTemplate};
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum ExportFormat {
+    #[default]
+    Flavor,
+    Pf2eStats,
+    Pdf,
+    /// One Obsidian note per NPC, written into the output directory
+    Obsidian,
+}
+
+impl ExportFormat {
+    pub fn is_binary(&self) -> bool {
+        matches!(self, ExportFormat::Pdf)
+    }
+}
+
+/// What an export format produces.
+pub enum Exported {
+    /// A single document, written to a file or stdout
+    Document(Vec<u8>),
+    /// One file per NPC as file name and contents, written into a folder
+    Files(Vec<(String, String)>),
+}
+
+/// One note per statblock, with file names derived from the NPC names.
+///
+/// Names that are taken by an earlier NPC of the batch get a numeric suffix,
+/// so exporting the same NPCs again gives the same file names.
+pub fn obsidian_notes(statblocks: &[Statblock]) -> Vec<(String, String)> {
+    let mut used_stems: HashSet<String> = HashSet::new();
+    statblocks
+        .iter()
+        .map(|statblock| {
+            let note = statblock.as_obsidian_note();
+            let base_stem = note.file_stem();
+            let stem = (1..)
+                .map(|counter| {
+                    if counter == 1 {
+                        base_stem.clone()
+                    } else {
+                        format!("{base_stem} {counter}")
+                    }
+                })
+                .find(|stem| !used_stems.contains(stem))
+                .unwrap();
+            used_stems.insert(stem.clone());
+            (format!("{stem}.md"), note.to_string())
+        })
+        .collect()
+}
+
+/// Writes the files into `folder`, replacing existing files, and returns the written paths.
+pub fn write_files(files: &[(String, String)], folder: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(folder).with_context(|| format!("Can't create folder {folder:?}"))?;
+    files
+        .iter()
+        .map(|(name, contents)| {
+            let path = folder.join(name);
+            fs::write(&path, contents).with_context(|| format!("Can't write {path:?}"))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+fn write_output(data: &[u8], output: Option<&Path>) -> Result<()> {
+    match output {
+        Some(path) => {
+            fs::write(path, data).with_context(|| format!("Can't write {path:?}"))?;
+        }
+        None => print!("{}", String::from_utf8_lossy(data)),
+    }
+    Ok(())
+}
+
+/// Renders the statblocks with a user template, either to `output` or to stdout.
+pub fn export_statblocks_with_template(
+    statblocks: &[Statblock],
+    template: &UserTemplate,
+    output: Option<&Path>,
+) -> Result<()> {
+    let rendered = statblocks
+        .iter()
+        .map(|x| template.render(x))
+        .collect::<Result<Vec<_>>>()?;
+    write_output(rendered.join("\n").as_bytes(), output)
+}
+
+/// The statblocks in the given format.
+pub fn render_statblocks(statblocks: &[Statblock], format: ExportFormat) -> Exported {
+    match format {
+        ExportFormat::Flavor => Exported::Document(
+            statblocks
+                .iter()
+                .map(|x| x.flavor().to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .into_bytes(),
+        ),
+        ExportFormat::Pf2eStats => Exported::Document(
+            statblocks
+                .iter()
+                .map(|x| x.as_pf2e_stats().to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .into_bytes(),
+        ),
+        ExportFormat::Pdf => Exported::Document(write_pdf_cards(statblocks)),
+        ExportFormat::Obsidian => Exported::Files(obsidian_notes(statblocks)),
+    }
+}
+
+/// Writes the statblocks in the given format, either to `output` or to stdout.
+/// Formats with one file per NPC write them into the `output` folder.
+pub fn export_statblocks(
+    statblocks: &[Statblock],
+    format: ExportFormat,
+    output: Option<&Path>,
+) -> Result<()> {
+    match (render_statblocks(statblocks, format), output) {
+        (Exported::Document(_), None) if format.is_binary() => {
+            Err(anyhow!("Format {format:?} requires an output path"))
+        }
+        (Exported::Document(data), output) => write_output(&data, output),
+        (Exported::Files(files), Some(folder)) => {
+            for path in write_files(&files, folder)? {
+                println!("{}", path.display());
+            }
+            Ok(())
+        }
+        (Exported::Files(_), None) => Err(anyhow!("Format {format:?} requires an output folder")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Statblock {
+        let mut statblock = Statblock::default();
+        statblock.set_name(name);
+        statblock
+    }
+
+    #[test]
+    fn test_obsidian_notes_are_stable() {
+        let statblocks = [named("Ezren"), named("Ezren"), named("Seelah")];
+        let names =
+            |files: &[(String, String)]| files.iter().map(|(x, _)| x.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            vec!["Ezren.md", "Ezren 2.md", "Seelah.md"],
+            names(&obsidian_notes(&statblocks))
+        );
+
+        let folder = tempfile::tempdir().unwrap();
+        for _ in 0..2 {
+            export_statblocks(&statblocks, ExportFormat::Obsidian, Some(folder.path())).unwrap();
+        }
+        let mut written = fs::read_dir(folder.path())
+            .unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        written.sort();
+        assert_eq!(vec!["Ezren 2.md", "Ezren.md", "Seelah.md"], written);
+        assert!(export_statblocks(&statblocks, ExportFormat::Obsidian, None).is_err());
+    }
+}
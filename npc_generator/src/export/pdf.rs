@@ -0,0 +1,433 @@
+use npc_generator_core::{AbilityStats, NamedElement, NpcFlavor, Statblock};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const PAGE_MARGIN: f32 = 36.0;
+
+const CARDS_PER_PAGE: usize = 2;
+const CARD_GAP: f32 = 18.0;
+const CARD_PADDING: f32 = 12.0;
+const COLUMN_GAP: f32 = 14.0;
+const STATS_COLUMN_RATIO: f32 = 0.45;
+
+const TITLE_SIZE: f32 = 14.0;
+const SUBTITLE_SIZE: f32 = 10.0;
+const BODY_SIZE: f32 = 9.0;
+const LINE_SPACING: f32 = 1.25;
+
+const REGULAR_FONT: Name = Name(b"F1");
+const BOLD_FONT: Name = Name(b"F2");
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum FontStyle {
+    Regular,
+    Bold,
+}
+
+impl FontStyle {
+    fn name(self) -> Name<'static> {
+        match self {
+            FontStyle::Regular => REGULAR_FONT,
+            FontStyle::Bold => BOLD_FONT,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum CardLine {
+    Text(FontStyle, f32, String),
+    Spacer(f32),
+}
+
+impl CardLine {
+    fn height(&self) -> f32 {
+        match self {
+            CardLine::Text(_, size, _) => size * LINE_SPACING,
+            CardLine::Spacer(height) => *height,
+        }
+    }
+}
+
+/// Writes the given statblocks as printable cards, two per A4 page.
+///
+/// Each card shows the stats on the left and the flavor text on the right.
+/// Only the standard PDF base fonts are used, so nothing has to be embedded.
+pub fn write_pdf_cards(statblocks: &[Statblock]) -> Vec<u8> {
+    let mut pdf = Pdf::new();
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let regular_font_id = Ref::new(3);
+    let bold_font_id = Ref::new(4);
+    let info_id = Ref::new(5);
+    let mut next_id = Ref::new(6);
+
+    let pages: Vec<&[Statblock]> = if statblocks.is_empty() {
+        vec![&[]]
+    } else {
+        statblocks.chunks(CARDS_PER_PAGE).collect()
+    };
+
+    let page_ids: Vec<(Ref, Ref)> = pages
+        .iter()
+        .map(|_| (next_id.bump(), next_id.bump()))
+        .collect();
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().map(|(page_id, _)| *page_id))
+        .count(page_ids.len() as i32);
+    pdf.document_info(info_id)
+        .title(TextStr("NPC Cards"))
+        .producer(TextStr("pf2e_npc_generator"));
+
+    for (cards, (page_id, content_id)) in pages.into_iter().zip(page_ids) {
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources()
+            .fonts()
+            .pair(REGULAR_FONT, regular_font_id)
+            .pair(BOLD_FONT, bold_font_id);
+        page.finish();
+
+        let mut content = Content::new();
+        let card_height =
+            (PAGE_HEIGHT - 2.0 * PAGE_MARGIN - (CARDS_PER_PAGE - 1) as f32 * CARD_GAP)
+                / CARDS_PER_PAGE as f32;
+        for (index, statblock) in cards.iter().enumerate() {
+            let top = PAGE_HEIGHT - PAGE_MARGIN - index as f32 * (card_height + CARD_GAP);
+            draw_card(
+                &mut content,
+                statblock,
+                Rect::new(
+                    PAGE_MARGIN,
+                    top - card_height,
+                    PAGE_WIDTH - PAGE_MARGIN,
+                    top,
+                ),
+            );
+        }
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.type1_font(regular_font_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_font_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    pdf.finish()
+}
+
+fn draw_card(content: &mut Content, statblock: &Statblock, area: Rect) {
+    let width = area.x2 - area.x1;
+
+    content.set_line_width(0.8);
+    content.set_stroke_gray(0.0);
+    content
+        .rect(area.x1, area.y1, width, area.y2 - area.y1)
+        .stroke();
+
+    let inner_left = area.x1 + CARD_PADDING;
+    let inner_width = width - 2.0 * CARD_PADDING;
+    let mut y = area.y2 - CARD_PADDING;
+
+    let header = header_lines(statblock, inner_width);
+    y = draw_lines(content, &header, inner_left, y, area.y1 + CARD_PADDING);
+
+    y -= BODY_SIZE * 0.5;
+    content.set_line_width(0.4);
+    content
+        .move_to(inner_left, y)
+        .line_to(inner_left + inner_width, y)
+        .stroke();
+    y -= BODY_SIZE * 0.5;
+
+    let stats_width = (inner_width - COLUMN_GAP) * STATS_COLUMN_RATIO;
+    let flavor_width = inner_width - COLUMN_GAP - stats_width;
+    let flavor_left = inner_left + stats_width + COLUMN_GAP;
+    let bottom = area.y1 + CARD_PADDING;
+
+    draw_lines(
+        content,
+        &stats_lines(statblock, stats_width),
+        inner_left,
+        y,
+        bottom,
+    );
+
+    content
+        .move_to(flavor_left - COLUMN_GAP / 2.0, y)
+        .line_to(flavor_left - COLUMN_GAP / 2.0, bottom)
+        .stroke();
+
+    draw_lines(
+        content,
+        &flavor_lines(statblock.flavor(), flavor_width),
+        flavor_left,
+        y,
+        bottom,
+    );
+}
+
+/// Draws the lines top to bottom and returns the baseline below the last one.
+///
+/// Lines that do not fit above `bottom` are dropped and the last visible line
+/// is replaced by an ellipsis.
+fn draw_lines(content: &mut Content, lines: &[CardLine], x: f32, top: f32, bottom: f32) -> f32 {
+    let mut y = top;
+    for (index, line) in lines.iter().enumerate() {
+        let height = line.height();
+        if y - height < bottom {
+            break;
+        }
+        let next_fits = lines
+            .get(index + 1)
+            .map(|next| y - height - next.height() >= bottom)
+            .unwrap_or(true);
+
+        if let CardLine::Text(style, size, text) = line {
+            let text = if next_fits { text.as_str() } else { "..." };
+            content.begin_text();
+            content.set_font(style.name(), *size);
+            content.next_line(x, y - size);
+            content.show(Str(&encode_win_ansi(text)));
+            content.end_text();
+        }
+        y -= height;
+
+        if !next_fits {
+            break;
+        }
+    }
+    y
+}
+
+fn header_lines(statblock: &Statblock, width: f32) -> Vec<CardLine> {
+    let mut lines = Vec::new();
+    let name = if statblock.name().is_empty() {
+        String::from("Unnamed NPC")
+    } else {
+        statblock.name().into_owned()
+    };
+    push_wrapped(&mut lines, FontStyle::Bold, TITLE_SIZE, &name, width);
+    push_wrapped(
+        &mut lines,
+        FontStyle::Bold,
+        SUBTITLE_SIZE,
+        &format!("{} {}", statblock.class(), statblock.level()),
+        width,
+    );
+
+    let mut traits = statblock
+        .traits()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    traits.sort();
+    let mut trait_line = String::from("Unique");
+    for trait_value in traits {
+        trait_line.push_str(", ");
+        trait_line.push_str(&trait_value);
+    }
+    push_wrapped(
+        &mut lines,
+        FontStyle::Regular,
+        BODY_SIZE,
+        &trait_line,
+        width,
+    );
+    lines
+}
+
+fn stats_lines(statblock: &Statblock, width: f32) -> Vec<CardLine> {
+    let mut lines = Vec::new();
+    let mut push =
+        |text: String| push_wrapped(&mut lines, FontStyle::Regular, BODY_SIZE, &text, width);
+
+    push(format!("Perception {:+}", statblock.perception()));
+    push(String::from("Languages Common"));
+
+    let mut skills = statblock
+        .skills()
+        .iter()
+        .filter(|x| x.1 != 0)
+        .collect::<Vec<_>>();
+    skills.sort_by_key(|x| x.0.to_string());
+    push(format!(
+        "Skills {}",
+        skills
+            .iter()
+            .map(|(skill, value)| format!("{skill} {value:+}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    let AbilityStats {
+        strength,
+        dexterity,
+        constitution,
+        intelligence,
+        wisdom,
+        charisma,
+    } = statblock.attributes().clone();
+    push(format!(
+        "Str {strength:+}, Dex {dexterity:+}, Con {constitution:+}, Int {intelligence:+}, Wis {wisdom:+}, Cha {charisma:+}"
+    ));
+    if !statblock.items().is_empty() {
+        push(format!("Items {}", statblock.items().join(", ")));
+    }
+    lines.push(CardLine::Spacer(BODY_SIZE * 0.5));
+
+    let mut push =
+        |text: String| push_wrapped(&mut lines, FontStyle::Regular, BODY_SIZE, &text, width);
+    push(format!(
+        "AC {}; Fort {:+}, Ref {:+}, Will {:+}",
+        statblock.armor_class(),
+        statblock.fortitude_save(),
+        statblock.reflex_save(),
+        statblock.will_save()
+    ));
+    push(format!("HP {}", statblock.hit_points()));
+    push(format!("Speed {} feet", statblock.land_speed()));
+
+    if let Some(ancestry) = statblock.ancestry() {
+        lines.push(CardLine::Spacer(BODY_SIZE * 0.5));
+        let ancestry_line = match statblock.heritage() {
+            Some(heritage) => format!("{} ({})", ancestry.name(), heritage.formatted_name()),
+            None => ancestry.name().into_owned(),
+        };
+        push_wrapped(
+            &mut lines,
+            FontStyle::Regular,
+            BODY_SIZE,
+            &format!(
+                "{ancestry_line}, {} years ({})",
+                statblock.age(),
+                statblock.age_range()
+            ),
+            width,
+        );
+    }
+    lines
+}
+
+fn flavor_lines(flavor: &NpcFlavor, width: f32) -> Vec<CardLine> {
+    let mut lines = Vec::new();
+    let paragraphs = [
+        Some(&flavor.description_line),
+        Some(&flavor.hair_and_eyes_line),
+        Some(&flavor.skin_line),
+        flavor.lineage_line.as_ref(),
+        Some(&flavor.size_and_build_line),
+        Some(&flavor.face_line),
+        Some(&flavor.habit_line),
+    ];
+    for paragraph in paragraphs.into_iter().flatten() {
+        if paragraph.is_empty() {
+            continue;
+        }
+        for text in paragraph.lines() {
+            push_wrapped(&mut lines, FontStyle::Regular, BODY_SIZE, text, width);
+        }
+        lines.push(CardLine::Spacer(BODY_SIZE * 0.4));
+    }
+    lines
+}
+
+fn push_wrapped(lines: &mut Vec<CardLine>, style: FontStyle, size: f32, text: &str, width: f32) {
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{current} {word}")
+        };
+        if !current.is_empty() && text_width(&candidate, style, size) > width {
+            lines.push(CardLine::Text(style, size, std::mem::take(&mut current)));
+            current = word.to_string();
+        } else {
+            current = candidate;
+        }
+    }
+    if !current.is_empty() {
+        lines.push(CardLine::Text(style, size, current));
+    }
+}
+
+/// Rough Helvetica metrics, good enough for line wrapping.
+fn text_width(text: &str, style: FontStyle, size: f32) -> f32 {
+    let em: f32 = text
+        .chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '!' | '|' | 'I' | ' ' => 0.28,
+            'f' | 't' | 'r' | '(' | ')' | '-' => 0.35,
+            'm' | 'w' | 'M' | 'W' => 0.85,
+            c if c.is_ascii_uppercase() => 0.68,
+            _ => 0.56,
+        })
+        .sum();
+    let weight = match style {
+        FontStyle::Regular => 1.0,
+        FontStyle::Bold => 1.06,
+    };
+    em * size * weight
+}
+
+/// Maps text onto WinAnsiEncoding, replacing everything it can't represent.
+fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\u{20}'..='\u{7e}' | '\u{a0}'..='\u{ff}' => c as u8,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201c}' => 0x93,
+            '\u{201d}' => 0x94,
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            '\u{2026}' => 0x85,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|x| *x == needle)
+            .count()
+    }
+
+    #[test]
+    fn test_two_cards_per_page() {
+        let statblocks = ["Zoë", "Łucja — the “Bold”", "龍"]
+            .into_iter()
+            .map(|name| {
+                let mut statblock = Statblock::default();
+                statblock.set_name(name);
+                statblock
+            })
+            .collect::<Vec<_>>();
+        let pdf = write_pdf_cards(&statblocks);
+
+        assert!(pdf.starts_with(b"%PDF"));
+        assert_eq!(1, count(&pdf, b"/Count 2"));
+        assert_eq!(2, count(&pdf, b"/Type /Page\n"));
+        // Non-ASCII strings are written in hex
+        assert_eq!(1, count(&pdf, b"<5A6FEB>"));
+        assert_eq!(
+            b"?ucja \x97 the \x93Bold\x94".to_vec(),
+            encode_win_ansi("Łucja — the “Bold”")
+        );
+        assert_eq!(b"?".to_vec(), encode_win_ansi("龍"));
+    }
+}
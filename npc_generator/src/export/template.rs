@@ -0,0 +1,279 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use log::{error, info};
+use minijinja::Environment;
+use npc_generator_core::{
+    AbilityStats, NamedElement, NpcFlavor, Proficiencies, Proficiency, Statblock,
+};
+use serde::Serialize;
+
+/// An output layout written by the user in the minijinja template language,
+/// compiled once when it's loaded.
+#[derive(Debug, Clone)]
+pub struct UserTemplate {
+    name: Arc<str>,
+    environment: Environment<'static>,
+}
+
+impl UserTemplate {
+    pub fn new(name: impl AsRef<str>, source: impl AsRef<str>) -> Result<Self> {
+        let name: Arc<str> = name.as_ref().into();
+        let mut environment = Environment::new();
+        environment.set_keep_trailing_newline(true);
+        environment.add_filter("signed", |value: i64| format!("{value:+}"));
+        environment
+            .add_template_owned(name.to_string(), source.as_ref().to_string())
+            .with_context(|| format!("Can't compile template '{name}'"))?;
+        Ok(Self { name, environment })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn render(&self, statblock: &Statblock) -> Result<String> {
+        self.environment
+            .get_template(&self.name)?
+            .render(TemplateContext::new(statblock))
+            .with_context(|| format!("Can't render template '{}'", self.name))
+    }
+}
+
+pub fn user_templates_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|mut path| {
+        path.push("pf2e_npc_generator");
+        path.push("templates");
+        path
+    })
+}
+
+/// Loads every file in the templates directory next to the weight presets,
+/// named after its file stem.
+pub fn load_user_templates() -> Result<Vec<Arc<UserTemplate>>> {
+    let Some(path) = user_templates_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.is_dir() {
+        info!("Template directory doesn't exist, trying to create it");
+        if let Err(x) = fs::create_dir_all(&path) {
+            error!("Error creating template directory: {x}");
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut templates = Vec::new();
+    for entry in path.read_dir()? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            info!("Skipping directory {path:?}");
+            continue;
+        }
+        let Some(name) = path.file_stem().map(|x| x.to_string_lossy().to_string()) else {
+            continue;
+        };
+        match fs::read_to_string(&path) {
+            Ok(source) => match UserTemplate::new(name, source) {
+                Ok(template) => templates.push(Arc::new(template)),
+                Err(x) => error!("{x:#}"),
+            },
+            Err(x) => error!("Can't read template {path:?}: {x}"),
+        }
+    }
+    templates.sort_by(|a, b| a.name().cmp(b.name()));
+    Ok(templates)
+}
+
+pub fn find_user_template(name: &str) -> Result<Arc<UserTemplate>> {
+    load_user_templates()?
+        .into_iter()
+        .find(|x| x.name() == name)
+        .ok_or_else(|| anyhow!("Given template '{name}' doesn't exist"))
+}
+
+#[derive(Serialize)]
+struct SkillContext {
+    name: String,
+    modifier: i16,
+    proficiency: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ElementContext {
+    name: String,
+    formatted_name: String,
+    traits: Vec<String>,
+    prd_reference: Option<String>,
+    lineage: Option<String>,
+}
+
+impl ElementContext {
+    fn new(
+        element: &impl NamedElement,
+        prd_reference: Option<&str>,
+        lineage: Option<&str>,
+    ) -> Self {
+        Self {
+            name: element.name().into_owned(),
+            formatted_name: element.formatted_name().into_owned(),
+            traits: element.traits().iter().map(ToString::to_string).collect(),
+            prd_reference: prd_reference.map(ToString::to_string),
+            lineage: lineage.map(ToString::to_string),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProficienciesContext {
+    perception: String,
+    fortitude_save: String,
+    reflex_save: String,
+    will_save: String,
+    unarmed: String,
+    simple_weapons: String,
+    martial_weapons: String,
+    advanced_weapons: String,
+    unarmored_defense: String,
+    light_armor: String,
+    medium_armor: String,
+    heavy_armor: String,
+}
+
+impl ProficienciesContext {
+    fn new(proficiencies: &Proficiencies) -> Self {
+        let name = |x: &Proficiency| format!("{x:?}");
+        Self {
+            perception: name(&proficiencies.perception),
+            fortitude_save: name(&proficiencies.fortitude_save),
+            reflex_save: name(&proficiencies.reflex_save),
+            will_save: name(&proficiencies.will_save),
+            unarmed: name(&proficiencies.unarmed),
+            simple_weapons: name(&proficiencies.simple_weapons),
+            martial_weapons: name(&proficiencies.martial_weapons),
+            advanced_weapons: name(&proficiencies.advanced_weapons),
+            unarmored_defense: name(&proficiencies.unarmored_defense),
+            light_armor: name(&proficiencies.light_armor),
+            medium_armor: name(&proficiencies.medium_armor),
+            heavy_armor: name(&proficiencies.heavy_armor),
+        }
+    }
+}
+
+/// Everything a template can access, with collections sorted the same way
+/// the built-in formats sort them.
+#[derive(Serialize)]
+struct TemplateContext<'a> {
+    name: String,
+    class: &'a str,
+    level: i8,
+    age: u64,
+    age_range: String,
+    sex: &'a str,
+    traits: Vec<String>,
+    perception: i16,
+    languages: Vec<&'a str>,
+    skills: Vec<SkillContext>,
+    attributes: &'a AbilityStats,
+    items: Vec<&'a str>,
+    armor_class: i16,
+    fortitude_save: i16,
+    reflex_save: i16,
+    will_save: i16,
+    hit_points: i32,
+    land_speed: u16,
+    ancestry: Option<ElementContext>,
+    heritage: Option<ElementContext>,
+    proficiencies: ProficienciesContext,
+    flavor: &'a NpcFlavor,
+}
+
+impl<'a> TemplateContext<'a> {
+    fn new(statblock: &'a Statblock) -> Self {
+        let mut traits = statblock
+            .traits()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        traits.sort();
+
+        let mut skills = statblock
+            .skills()
+            .iter()
+            .map(|(skill, modifier)| SkillContext {
+                name: skill.to_string(),
+                modifier: *modifier,
+                proficiency: statblock
+                    .proficiencies()
+                    .skills
+                    .get(skill)
+                    .map(|x| format!("{x:?}")),
+            })
+            .collect::<Vec<_>>();
+        skills.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            name: statblock.name().into_owned(),
+            class: statblock.class(),
+            level: statblock.level(),
+            age: statblock.age(),
+            age_range: statblock.age_range().to_string(),
+            sex: statblock.sex(),
+            traits,
+            perception: statblock.perception(),
+            languages: vec!["Common"],
+            skills,
+            attributes: statblock.attributes(),
+            items: statblock.items().iter().map(|x| x.as_ref()).collect(),
+            armor_class: statblock.armor_class(),
+            fortitude_save: statblock.fortitude_save(),
+            reflex_save: statblock.reflex_save(),
+            will_save: statblock.will_save(),
+            hit_points: statblock.hit_points(),
+            land_speed: statblock.land_speed(),
+            ancestry: statblock
+                .ancestry()
+                .map(|x| ElementContext::new(x, x.prd_reference(), None)),
+            heritage: statblock
+                .heritage()
+                .map(|x| ElementContext::new(x, x.prd_reference(), x.lineage())),
+            proficiencies: ProficienciesContext::new(statblock.proficiencies()),
+            flavor: statblock.flavor(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_statblock() {
+        let template = UserTemplate::new(
+            "card",
+            "{{ name }} ({{ class }} {{ level }}), Perception {{ perception | signed }}\n",
+        )
+        .unwrap();
+        let mut statblock = Statblock::default();
+        statblock.set_name("Ezren");
+
+        assert_eq!("card", template.name());
+        assert_eq!(
+            format!(
+                "Ezren ({} {}), Perception {:+}\n",
+                statblock.class(),
+                statblock.level(),
+                statblock.perception()
+            ),
+            template.render(&statblock).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let error = UserTemplate::new("broken", "{{ name ").unwrap_err();
+        assert_eq!("Can't compile template 'broken'", error.to_string());
+    }
+}
@@ -7,17 +7,17 @@ use indicatif::ParallelProgressIterator;
 use indicatif::ProgressIterator;
 use indicatif::ProgressStyle;
 use log::{error, info};
-use native_dialog::FileDialog;
 use npc_generator_core::generators::{GeneratorData, GeneratorScripts};
+use npc_generator_core::loader::{self, DataSource, DirectorySource, ZipSource};
 use npc_generator_core::weight_presets::WeightPreset;
 use npc_generator_core::{generators::Generator, *};
+use rand::seq::SliceRandom;
 use rand::SeedableRng;
 #[cfg(feature = "rayon")]
 use rayon::iter::IntoParallelIterator;
 #[cfg(feature = "rayon")]
 use rayon::iter::ParallelIterator;
 use std::collections::{HashMap, LinkedList};
-use std::fs::File;
 use std::path::Path;
 use std::{
     error::Error,
@@ -25,10 +25,18 @@ use std::{
     path::PathBuf,
     sync::{Arc, Mutex},
 };
-use std::{io, usize};
 mod config;
+use config::{config_path, Config};
+mod export;
+mod pack_builder;
+mod packs;
+use packs::{install_pack, installed_packs, InstallMode};
 mod ui;
 use anyhow::{anyhow, Context, Result};
+use export::{
+    export_statblocks, export_statblocks_with_template, find_user_template, load_user_templates,
+    ExportFormat,
+};
 
 #[cfg(not(feature = "rayon"))]
 fn generate_iterator(range: std::ops::Range<usize>) -> impl Iterator<Item = usize> {
@@ -40,22 +48,131 @@ fn generate_iterator(range: std::ops::Range<usize>) -> rayon::range::Iter<usize>
     range.into_par_iter()
 }
 
-fn generate_distribution_preview(sample_size: u64, preset_name: Option<&str>) -> Result<()> {
+fn find_weight_preset(preset_name: Option<&str>) -> Result<Option<Arc<WeightPreset>>> {
     let weight_presets = load_weight_presets()?;
     let current_weight_preset = if let Some(preset_name) = preset_name {
-        weight_presets.iter().find(|x| x.name() == preset_name)
+        weight_presets.into_iter().find(|x| x.name() == preset_name)
     } else {
         None
     };
 
-    if preset_name.is_some() && current_weight_preset.is_none() {
-        return Err(anyhow!(
-            "Given preset '{}' doesn't exist",
-            preset_name.unwrap()
-        ));
+    if let (Some(preset_name), None) = (preset_name, &current_weight_preset) {
+        return Err(anyhow!("Given preset '{}' doesn't exist", preset_name));
+    }
+    Ok(current_weight_preset)
+}
+
+/// Exports with the named user template if given, otherwise in `format`.
+fn export(
+    statblocks: &[Statblock],
+    format: ExportFormat,
+    template_name: Option<&str>,
+    output: Option<&Path>,
+) -> Result<()> {
+    match template_name {
+        Some(name) => {
+            let template = find_user_template(name)?;
+            export_statblocks_with_template(statblocks, &template, output)
+        }
+        None => export_statblocks(statblocks, format, output),
+    }
+}
+
+/// The archetypes of the given level, empty if no level is given.
+fn level_archetypes(generator_data: &GeneratorData, level: Option<i8>) -> Result<Vec<Archetype>> {
+    let Some(level) = level else {
+        return Ok(Vec::new());
+    };
+    let archetypes = generator_data
+        .archetypes
+        .iter()
+        .filter(|x| x.level() == level)
+        .cloned()
+        .collect::<Vec<_>>();
+    if archetypes.is_empty() {
+        return Err(anyhow!("No archetype has level {level}"));
+    }
+    Ok(archetypes)
+}
+
+fn generate_npcs(
+    count: usize,
+    config: &Config,
+    template_name: Option<&str>,
+    output: Option<&Path>,
+    packs: &[PathBuf],
+) -> Result<()> {
+    if let Some(name) = template_name {
+        find_user_template(name)?;
+    }
+    let current_weight_preset = find_weight_preset(config.preset.as_deref())?;
+    let (generator_data, generator_scripts) = load_generator_data(packs, false)?;
+
+    let mut generator = Generator::new(
+        rand::rngs::StdRng::from_rng(rand::thread_rng())?,
+        generator_data,
+        generator_scripts,
+    )
+    .ok_or_else(|| anyhow!("Can't create generator"))?;
+
+    let archetypes = level_archetypes(&generator.data, config.level)?;
+
+    let mut rng = rand::thread_rng();
+    let statblocks = (0..count)
+        .map(|_| {
+            let npc_options = NpcOptions {
+                archetype: archetypes.choose(&mut rng).cloned(),
+                enable_flavor_text: config.flavor.unwrap_or(true),
+                units: config.units,
+                ..Default::default()
+            };
+            generator.generate(&npc_options, current_weight_preset.clone())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    export(&statblocks, config.format, template_name, output)
+}
+
+fn convert_statblocks(
+    input: &Path,
+    format: ExportFormat,
+    template_name: Option<&str>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let text = fs::read_to_string(input).with_context(|| format!("Can't read {input:?}"))?;
+    let statblocks = PF2eStats::parse_all(&text)
+        .with_context(|| format!("Can't parse {input:?}"))?
+        .into_iter()
+        .map(Statblock::from)
+        .collect::<Vec<_>>();
+
+    export(&statblocks, format, template_name, output)
+}
+
+/// Prints every inconsistency of the data pack at `path`, a data directory or zip file.
+fn lint_generator_data(path: &Path) -> Result<()> {
+    let (generator_data, generator_scripts) =
+        loader::read_generator_data(open_data_source(path)?.as_mut())?;
+
+    let issues = generator_data.lint(&generator_scripts);
+    for issue in &issues {
+        println!("{issue}");
+    }
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Found {} issue(s) in {path:?}", issues.len()))
     }
+}
 
-    let (generator_data, generator_scripts) = load_generator_data()?;
+fn generate_distribution_preview(
+    sample_size: u64,
+    config: &Config,
+    packs: &[PathBuf],
+) -> Result<()> {
+    let current_weight_preset = find_weight_preset(config.preset.as_deref())?;
+    let (generator_data, generator_scripts) = load_generator_data(packs, false)?;
+    let archetypes = level_archetypes(&generator_data, config.level)?;
 
     let (results, heritages, errors, elapsed) = {
         let results: HashMap<String, usize> = HashMap::new();
@@ -90,10 +207,12 @@ fn generate_distribution_preview(sample_size: u64, preset_name: Option<&str>) ->
             })
             .for_each(|mut generator| {
                 let npc_options = NpcOptions {
-                    enable_flavor_text: false,
+                    archetype: archetypes.choose(&mut rand::thread_rng()).cloned(),
+                    enable_flavor_text: config.flavor.unwrap_or(false),
+                    units: config.units,
                     ..Default::default()
                 };
-                match generator.generate(&npc_options, current_weight_preset.cloned()) {
+                match generator.generate(&npc_options, current_weight_preset.clone()) {
                     Ok(result) => {
                         let ancestry = result.ancestry().unwrap();
                         let heritage = result.heritage();
@@ -173,7 +292,7 @@ fn generate_distribution_preview(sample_size: u64, preset_name: Option<&str>) ->
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn generate_character() -> Result<(), Box<dyn Error>> {
+fn generate_character(config: &Config, packs: &[PathBuf]) -> Result<(), Box<dyn Error>> {
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([400.0, 300.0])
@@ -182,8 +301,10 @@ fn generate_character() -> Result<(), Box<dyn Error>> {
     };
 
     let weight_presets = load_weight_presets()?;
+    let user_templates = load_user_templates()?;
 
-    let (generator_data, generator_scripts) = load_generator_data()?;
+    let (generator_data, generator_scripts) = load_generator_data(packs, true)?;
+    let config = config.clone();
     eframe::run_native(
         "Character Generator",
         native_options,
@@ -193,6 +314,8 @@ fn generate_character() -> Result<(), Box<dyn Error>> {
                 generator_data.clone(),
                 generator_scripts,
                 weight_presets,
+                user_templates,
+                &config,
             ))
         }),
     )?;
@@ -200,129 +323,94 @@ fn generate_character() -> Result<(), Box<dyn Error>> {
 }
 
 fn show_open_zip_dialog() -> Result<Option<PathBuf>> {
-    Ok(FileDialog::new()
-        .set_location("~/Desktop")
+    Ok(native_dialog::FileDialog::new()
         .add_filter("Generator Source Data Package", &["zip"])
         .show_open_single_file()?)
 }
 
-fn load_generator_data_from_zip(
-    zip_path: impl AsRef<Path>,
-) -> Result<(Arc<GeneratorData>, Arc<GeneratorScripts>)> {
-    let file = File::open(zip_path)?;
-
-    let mut zip = zip::ZipArchive::new(file)?;
-
-    fn read_from_zip<T, R>(zip: &mut zip::ZipArchive<R>, name: &str) -> Result<T>
-    where
-        T: for<'a> serde::Deserialize<'a>,
-        R: io::Seek + io::Read,
-    {
-        let file = zip
-            .by_name(name)
-            .with_context(|| format!("Can't read {name} from zip file"))?;
-        let data = io::read_to_string(file)?;
-        ron::from_str(&data).with_context(|| format!("Can't deserialize {name}"))
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|x| x.eq_ignore_ascii_case("zip"))
+}
+
+/// Opens a data pack, either a zip file, a directory with a `data` folder or
+/// the data directory itself.
+fn open_data_source(pack: &Path) -> Result<Box<dyn DataSource>> {
+    if is_zip(pack) {
+        Ok(Box::new(ZipSource::open(pack)?))
+    } else {
+        Ok(Box::new(DirectorySource::new(packs::data_directory(pack))))
     }
+}
 
-    let generator_data = {
-        let ancestries: WeightMap<Ancestry> = read_from_zip(&mut zip, "ancestries.ron")?;
-        let heritages: WeightMap<Heritage> = read_from_zip(&mut zip, "heritages.ron")?;
-        let backgrounds: WeightMap<Background> = read_from_zip(&mut zip, "backgrounds.ron")?;
-        let names: HashMap<Trait, HashMap<String, WeightMap<String>>> =
-            read_from_zip(&mut zip, "names.ron")?;
-
-        let archetypes: Vec<Archetype> = {
-            let mut archetypes: Vec<Archetype> = read_from_zip(&mut zip, "archetypes.ron")?;
-            archetypes.sort_by_key(|x| x.level());
-            archetypes
-        };
+/// Environment variable with the data pack to use, like `--data`.
+const DATA_ENV_VAR: &str = "NPC_GENERATOR_DATA";
 
-        Arc::new(npc_generator_core::generators::GeneratorData {
-            ancestries,
-            versitile_heritages: heritages,
-            normal_heritage_weight: 0.8,
-            backgrounds,
-            heritages: Default::default(),
-            names,
-            archetypes,
-        })
-    };
+/// Where older versions extracted the zip file picked in the file dialog.
+fn legacy_data_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|mut path| {
+        path.push("pf2e_npc_generator");
+        path.push("generator_data");
+        path
+    })
+}
 
-    let generator_scripts = {
-        Arc::new(GeneratorScripts {
-            default_format_flavor_description_line_script: io::read_to_string(
-                zip.by_name("scripts/default_format_flavor_description_line.glu")?,
-            )?,
-        })
-    };
+fn list_packs() -> Result<()> {
+    let packs = installed_packs()?;
+    if packs.is_empty() {
+        println!("No data packs installed");
+    }
+    let active = packs::active_pack_name()?;
+    for pack in packs {
+        let marker = if active.as_ref() == Some(&pack.name) {
+            " (active)"
+        } else {
+            ""
+        };
+        println!("{} {}{marker}", pack.name, pack.manifest.version);
+    }
+    Ok(())
+}
 
-    Ok((generator_data, generator_scripts))
+fn load_pack(pack: &Path) -> Result<(Arc<GeneratorData>, Arc<GeneratorScripts>)> {
+    let (generator_data, generator_scripts) =
+        loader::load_generator_data(open_data_source(pack)?.as_mut())?;
+    Ok((Arc::new(generator_data), Arc::new(generator_scripts)))
 }
 
-fn load_generator_data_from_directory(
-    base_path: impl AsRef<Path>,
-) -> Result<(Arc<GeneratorData>, Arc<GeneratorScripts>)> {
-    let base_path = base_path.as_ref();
-    info!("Loading generator data from {base_path:?}");
-    let generator_data = {
-        let mut data: PathBuf = base_path.into();
-        data.push("data");
-
-        fn read_file<T>(data: &Path, name: &str) -> Result<T>
-        where
-            T: for<'a> serde::Deserialize<'a>,
-        {
-            let mut path: PathBuf = data.into();
-            path.push(name);
-            info!("Trying to read {data:?}");
-            let data = fs::read_to_string(&path)
-                .with_context(|| format!("Can't read {name} from {path:?}"))?;
-            ron::from_str(&data).with_context(|| format!("Can't deserialize {name}"))
+/// The `data` directory of the repository, compiled into the binary.
+#[cfg(feature = "embedded-data")]
+static EMBEDDED_DATA: include_dir::Dir<'static> =
+    include_dir::include_dir!("$CARGO_MANIFEST_DIR/../data");
+
+#[cfg(feature = "embedded-data")]
+fn load_embedded_generator_data() -> Result<(Arc<GeneratorData>, Arc<GeneratorScripts>)> {
+    fn insert_dir(source: &mut loader::MemorySource, dir: &include_dir::Dir) {
+        for file in dir.files() {
+            if let Some(contents) = file.contents_utf8() {
+                source.insert(file.path().to_string_lossy(), contents);
+            }
         }
+        for dir in dir.dirs() {
+            insert_dir(source, dir);
+        }
+    }
 
-        let ancestries: WeightMap<Ancestry> = read_file(&data, "ancestries.ron")?;
-        let heritages: WeightMap<Heritage> = read_file(&data, "heritages.ron")?;
-        let backgrounds: WeightMap<Background> = read_file(&data, "backgrounds.ron")?;
-        let names: HashMap<Trait, HashMap<String, WeightMap<String>>> =
-            read_file(&data, "names.ron")?;
-
-        let archetypes = {
-            let mut archetypes: Vec<Archetype> = read_file(&data, "archetypes.ron")?;
-            archetypes.sort_by_key(|x| x.level());
-            archetypes
-        };
-
-        Arc::new(npc_generator_core::generators::GeneratorData {
-            ancestries,
-            versitile_heritages: heritages,
-            normal_heritage_weight: 0.8,
-            backgrounds,
-            heritages: Default::default(),
-            names,
-            archetypes,
-        })
-    };
-
-    info!("Reading scripts...");
-
-    let generator_scripts = {
-        let mut scripts: PathBuf = base_path.into();
-        scripts.push("data");
-        scripts.push("scripts");
-        let scripts = scripts;
-
-        Arc::new(GeneratorScripts {
-            default_format_flavor_description_line_script: io::read_to_string({
-                let mut path = scripts.clone();
-                path.push("default_format_flavor_description_line.glu");
-                info!("Reading script file {path:?}");
-                File::open(path)?
-            })?,
-        })
-    };
+    let mut source = loader::MemorySource::new("embedded data");
+    insert_dir(&mut source, &EMBEDDED_DATA);
+    let (generator_data, generator_scripts) = loader::load_generator_data(&mut source)?;
+    Ok((Arc::new(generator_data), Arc::new(generator_scripts)))
+}
 
-    Ok((generator_data, generator_scripts))
+fn load_layered_generator_data(
+    packs: &[PathBuf],
+) -> Result<(Arc<GeneratorData>, Arc<GeneratorScripts>)> {
+    let mut sources = packs
+        .iter()
+        .map(|x| open_data_source(x))
+        .collect::<Result<Vec<_>>>()?;
+    let (generator_data, generator_scripts) = loader::load_layered_generator_data(&mut sources)?;
+    Ok((Arc::new(generator_data), Arc::new(generator_scripts)))
 }
 
 fn load_weight_presets() -> Result<Vec<Arc<WeightPreset>>> {
@@ -344,97 +432,75 @@ fn load_weight_presets() -> Result<Vec<Arc<WeightPreset>>> {
             None
         }
     } {
-        let mut weight_presets = Vec::new();
-        fn add_entry(
-            weight_presets: &mut Vec<Arc<WeightPreset>>,
-            entry: Result<fs::DirEntry, io::Error>,
-        ) -> Result<(), anyhow::Error> {
-            let entry = entry?;
-            let path = entry.path();
-
-            if entry.file_type()?.is_dir() {
-                info!("Skipping directory {path:?}");
-                return Ok(());
-            }
-
-            if !path.extension().map(|x| x == "ron").unwrap_or(false) {
-                return Err(anyhow!("Unknown file: {path:?}"));
-            }
-
-            weight_presets.push(ron::from_str(&fs::read_to_string(path)?)?);
-            Ok(())
-        }
-        for entry in weight_preset_path.read_dir()? {
-            if let Err(x) = add_entry(&mut weight_presets, entry) {
-                error!("{x}");
-            }
-        }
-        Ok(weight_presets)
+        Ok(loader::load_weight_presets(&weight_preset_path)?)
     } else {
         Ok(Vec::new())
     }
 }
 
-fn load_generator_data() -> Result<(Arc<GeneratorData>, Arc<GeneratorScripts>)> {
-    let generator_data = std::env::current_dir().map(load_generator_data_from_directory);
-    fn handle_err() -> Result<(Arc<GeneratorData>, Arc<GeneratorScripts>)> {
-        let persistent_generator_config_path = {
-            if let Some(path) = dirs::data_dir() {
-                let mut path = path;
-                path.push("pf2e_npc_generator");
-                path.push("generator_data");
-                if path.exists() && path.is_dir() {
-                    Some(path)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        };
+/// Loads the given packs, or searches the current directory, the installed pack
+/// and the embedded data. Only `interactive` callers fall back to a file dialog.
+fn load_generator_data(
+    packs: &[PathBuf],
+    interactive: bool,
+) -> Result<(Arc<GeneratorData>, Arc<GeneratorScripts>)> {
+    if !packs.is_empty() {
+        return load_layered_generator_data(packs)
+            .with_context(|| format!("Can't load the data packs {packs:?}"));
+    }
 
-        if let Some(path) = persistent_generator_config_path {
-            match load_generator_data_from_directory(path) {
-                Ok(data) => return Ok(data),
-                Err(err) => error!("{}", err),
+    let mut failures = Vec::new();
+    let active_pack = packs::active_pack_path().unwrap_or_else(|err| {
+        failures.push(format!("active pack: {err:#}"));
+        None
+    });
+    let candidates = [
+        std::env::current_dir().ok(),
+        active_pack,
+        legacy_data_path(),
+    ];
+    for path in candidates.into_iter().flatten() {
+        match load_pack(&path) {
+            Ok(data) => return Ok(data),
+            Err(err) => {
+                info!("No generator data in {path:?}: {err:#}");
+                failures.push(format!("{path:?}: {err:#}"));
             }
         }
+    }
 
-        if let Some(file_path) = show_open_zip_dialog()? {
-            let data = load_generator_data_from_zip(&file_path)?;
-            if let Some(path) = dirs::data_dir() {
-                let mut path = path;
-                path.push("pf2e_npc_generator");
-                path.push("generator_data");
-                path.push("data");
-                match fs::create_dir_all(&path)
-                    .and_then(|()| File::open(file_path))
-                    .and_then(|file| Ok(zip::ZipArchive::new(file)?))
-                    .and_then(|mut file| Ok(file.extract(path)?))
-                {
-                    Ok(()) => {}
-                    Err(err) => {
-                        error!("Couldn't persist generator data: {}", err);
+    #[cfg(feature = "embedded-data")]
+    match load_embedded_generator_data() {
+        Ok(data) => {
+            info!("Using the generator data embedded in the binary");
+            return Ok(data);
+        }
+        Err(err) => failures.push(format!("embedded data: {err:#}")),
+    }
+
+    if interactive {
+        match show_open_zip_dialog()? {
+            Some(file_path) => {
+                let data = load_pack(&file_path)?;
+                match install_pack(&file_path, InstallMode::Replace) {
+                    Ok(pack) => {
+                        if let Err(err) = packs::use_pack(&pack.name) {
+                            error!("Couldn't activate pack `{}`: {err:#}", pack.name);
+                        }
                     }
+                    Err(err) => error!("Couldn't install generator data: {err:#}"),
                 }
+                return Ok(data);
             }
-            Ok(data)
-        } else {
-            Err(anyhow!("No zip file selected in file dialog"))
+            None => failures.push(String::from("file dialog: no zip file selected")),
         }
     }
-    match generator_data {
-        Ok(Ok(data)) => Ok(data),
 
-        Ok(Err(err)) => {
-            error!("{}", err);
-            handle_err()
-        }
-        Err(err) => {
-            error!("{}", err);
-            handle_err()
-        }
-    }
+    Err(anyhow!(
+        "No generator data found, tried:\n  {}\nGive a data pack with --data or {DATA_ENV_VAR}, \
+        or install one with --mode install --input <zip>",
+        failures.join("\n  ")
+    ))
 }
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -442,6 +508,26 @@ enum Mode {
     #[default]
     Interactive,
     Statistics,
+    Generate,
+    /// Reads pf2e-stats blocks from the input file and exports them in another format
+    Convert,
+    /// Checks the data pack given as input for inconsistencies
+    Lint,
+    /// Prints the configuration after applying the command line arguments
+    Config,
+    /// Lists the installed data packs
+    Packs,
+    /// Installs the data pack zip given as input, so it's found without --data
+    Install,
+    /// Replaces an installed data pack with the zip given as input
+    Update,
+    /// Removes the installed data pack given with --name
+    Remove,
+    /// Makes the installed data pack given with --name the active one
+    Use,
+    /// Validates the data directory given as input and writes it as a zip
+    /// with a manifest to the output file
+    Pack,
 }
 
 #[derive(Parser, Debug)]
@@ -453,21 +539,200 @@ struct Args {
     #[arg(short, long, default_value_t = 2_000_000)]
     sample_size: u64,
 
+    /// Weight preset to use, overrides the config file
     #[arg(long, default_value = None)]
     preset: Option<String>,
+
+    /// Number of NPCs to generate in generate mode
+    #[arg(short, long, default_value_t = 1)]
+    count: usize,
+
+    /// Output format of generate mode, overrides the config file
+    #[arg(short, long, value_enum)]
+    format: Option<ExportFormat>,
+
+    /// Whether to generate flavor text, overrides the config file
+    #[arg(long)]
+    flavor: Option<bool>,
+
+    /// Level of the archetype generated NPCs use, overrides the config file
+    #[arg(short, long, allow_hyphen_values = true)]
+    level: Option<i8>,
+
+    /// Units of heights and weights in flavor text, imperial or metric.
+    /// Overrides the config file
+    #[arg(long)]
+    units: Option<Units>,
+
+    /// Name of a user template to render instead of the output format.
+    /// Templates are read from the templates folder in the config directory
+    #[arg(short, long)]
+    template: Option<String>,
+
+    /// File to write the generated NPCs to, stdout if not given.
+    /// For the obsidian format this is the vault folder, for pack mode the zip file
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Data pack directory or zip file to use, overrides the config file
+    #[arg(long, env = DATA_ENV_VAR)]
+    data: Option<PathBuf>,
+
+    /// Data pack directory or zip file, can be repeated. The first pack is
+    /// the base, later packs add, replace or remove entries in order
+    #[arg(long)]
+    pack: Vec<PathBuf>,
+
+    /// Name of an installed data pack, for remove and use mode
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Markdown file with pf2e-stats blocks to read in convert mode, the data
+    /// directory or zip file to check in lint mode, the zip file to install
+    /// or the data directory to pack
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+}
+
+impl Args {
+    /// The config file with the settings given as arguments replaced.
+    fn effective_config(&self) -> Result<Config> {
+        let mut config = Config::load()?;
+        if let Some(ref data) = self.data {
+            config.data = Some(data.clone());
+        }
+        if let Some(ref preset) = self.preset {
+            config.preset = Some(preset.clone());
+        }
+        if let Some(format) = self.format {
+            config.format = format;
+        }
+        if let Some(flavor) = self.flavor {
+            config.flavor = Some(flavor);
+        }
+        if let Some(level) = self.level {
+            config.level = Some(level);
+        }
+        if let Some(units) = self.units {
+            config.units = units;
+        }
+        Ok(config)
+    }
+
+    /// The packs given as arguments, or the data path of the config.
+    fn packs(&self, config: &Config) -> Vec<PathBuf> {
+        if self.pack.is_empty() {
+            config.data.iter().cloned().collect()
+        } else {
+            self.pack.clone()
+        }
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), Box<dyn Error>> {
     pretty_env_logger::init();
     let args = Args::parse();
+    let config = args.effective_config()?;
+    let packs = args.packs(&config);
     match args.mode {
         Mode::Statistics => {
-            generate_distribution_preview(args.sample_size, args.preset.as_deref())?;
+            generate_distribution_preview(args.sample_size, &config, &packs)?;
             Ok(())
         }
         Mode::Interactive => {
-            generate_character()?;
+            generate_character(&config, &packs)?;
+            Ok(())
+        }
+        Mode::Generate => {
+            generate_npcs(
+                args.count,
+                &config,
+                args.template.as_deref(),
+                args.output.as_deref(),
+                &packs,
+            )?;
+            Ok(())
+        }
+        Mode::Convert => {
+            let input = args
+                .input
+                .as_deref()
+                .ok_or_else(|| anyhow!("Convert mode requires an --input file"))?;
+            convert_statblocks(
+                input,
+                config.format,
+                args.template.as_deref(),
+                args.output.as_deref(),
+            )?;
+            Ok(())
+        }
+        Mode::Lint => {
+            let input = args
+                .input
+                .as_deref()
+                .ok_or_else(|| anyhow!("Lint mode requires an --input data pack"))?;
+            lint_generator_data(input)?;
+            Ok(())
+        }
+        Mode::Install | Mode::Update => {
+            let input = args
+                .input
+                .as_deref()
+                .ok_or_else(|| anyhow!("This mode requires an --input zip file"))?;
+            if !is_zip(input) {
+                return Err(anyhow!("Only zip files can be installed, got {input:?}").into());
+            }
+            let mode = if args.mode == Mode::Update {
+                InstallMode::Update
+            } else {
+                InstallMode::Install
+            };
+            let pack = install_pack(input, mode)?;
+            let action = if mode == InstallMode::Update {
+                "Updated"
+            } else {
+                "Installed"
+            };
+            println!(
+                "{action} `{}` {} in {:?}",
+                pack.name, pack.manifest.version, pack.path
+            );
+            Ok(())
+        }
+        Mode::Pack => {
+            let input = args
+                .input
+                .as_deref()
+                .ok_or_else(|| anyhow!("Pack mode requires an --input data directory"))?;
+            let output = pack_builder::build_pack(input, args.output.as_deref())?;
+            println!("Wrote {output:?}");
+            Ok(())
+        }
+        Mode::Packs => {
+            list_packs()?;
+            Ok(())
+        }
+        Mode::Remove | Mode::Use => {
+            let name = args
+                .name
+                .as_deref()
+                .ok_or_else(|| anyhow!("This mode requires the --name of an installed pack"))?;
+            if args.mode == Mode::Remove {
+                packs::remove_pack(name)?;
+                println!("Removed `{name}`");
+            } else {
+                packs::use_pack(name)?;
+                println!("Using `{name}`");
+            }
+            Ok(())
+        }
+        Mode::Config => {
+            match config_path() {
+                Some(path) => println!("// Config file: {path:?}"),
+                None => println!("// No config directory on this system"),
+            }
+            println!("{}", config.to_ron()?);
             Ok(())
         }
     }
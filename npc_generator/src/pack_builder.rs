@@ -0,0 +1,191 @@
+//! Building distributable data pack zips from data directories.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use log::info;
+use npc_generator_core::{
+    checksum,
+    loader::{self, MemorySource},
+    PackManifest, CURRENT_SCHEMA_VERSION, MANIFEST_FILE,
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipWriter};
+
+use crate::packs::data_directory;
+
+/// Version written into the manifest of packs that don't have one yet.
+const INITIAL_PACK_VERSION: &str = "0.1.0";
+
+/// Extensions of the files the loader reads, data files and scripts.
+const PACK_FILE_EXTENSIONS: [&str; 2] = ["ron", "glu"];
+
+/// Reads every pack file below `dir`, keyed by its `/` separated path relative to `root`.
+fn collect_files(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) -> Result<()> {
+    for entry in dir.read_dir()? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, files)?;
+            continue;
+        }
+        if !path
+            .extension()
+            .is_some_and(|x| PACK_FILE_EXTENSIONS.iter().any(|y| x == *y))
+        {
+            info!("Skipping {path:?}, it isn't a data file or script");
+            continue;
+        }
+        let relative_path = path
+            .strip_prefix(root)?
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let contents =
+            fs::read_to_string(&path).with_context(|| format!("Can't read {path:?} as text"))?;
+        files.insert(relative_path, contents);
+    }
+    Ok(())
+}
+
+/// Writes the files in the given order with fixed metadata, so the same
+/// files always give the same zip.
+fn write_zip(path: &Path, files: &BTreeMap<String, String>) -> Result<()> {
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+    let mut zip =
+        ZipWriter::new(fs::File::create(path).with_context(|| format!("Can't create {path:?}"))?);
+    for (name, contents) in files {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Reads the pack files in `input`, migrated to the current schema version,
+/// with a manifest listing their checksums.
+fn read_pack_files(input: &Path) -> Result<(PathBuf, PackManifest, BTreeMap<String, String>)> {
+    let data_path = data_directory(input);
+    if !data_path.is_dir() {
+        return Err(anyhow!("{input:?} isn't a data directory"));
+    }
+
+    let mut files = BTreeMap::new();
+    collect_files(&data_path, &data_path, &mut files)?;
+
+    let mut manifest = match files.remove(MANIFEST_FILE) {
+        Some(x) => ron::from_str::<PackManifest>(&x)
+            .with_context(|| format!("Can't parse the {MANIFEST_FILE} in {data_path:?}"))?,
+        None => {
+            let name = input
+                .canonicalize()?
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default();
+            eprintln!("No {MANIFEST_FILE} found, writing one for `{name}` {INITIAL_PACK_VERSION}");
+            PackManifest {
+                version: INITIAL_PACK_VERSION.to_string(),
+                ..PackManifest::legacy(name)
+            }
+        }
+    };
+    manifest.check_compatibility()?;
+
+    for (file, contents) in files.iter_mut() {
+        *contents = manifest.migrate(file, contents).into_owned();
+    }
+    manifest.schema_version = CURRENT_SCHEMA_VERSION;
+    manifest.checksums = files
+        .iter()
+        .map(|(file, contents)| (file.clone(), checksum(contents.as_bytes())))
+        .collect();
+    files.insert(
+        MANIFEST_FILE.to_string(),
+        ron::ser::to_string_pretty(
+            &manifest,
+            ron::ser::PrettyConfig::default().struct_names(true),
+        )?,
+    );
+    Ok((data_path, manifest, files))
+}
+
+/// Validates the data pack in `input` and writes it as a zip with a manifest
+/// listing the checksums of all files.
+///
+/// `input` is either the data directory or a directory containing a `data`
+/// directory. Files of older schema versions are migrated, the input itself
+/// is left untouched. Returns the path of the written zip.
+pub fn build_pack(input: &Path, output: Option<&Path>) -> Result<PathBuf> {
+    let (data_path, manifest, files) = read_pack_files(input)?;
+    let mut source = MemorySource::new(data_path.display().to_string());
+    for (file, contents) in &files {
+        source.insert(file, contents);
+    }
+    let (generator_data, generator_scripts) = loader::load_generator_data(&mut source)?;
+    for issue in generator_data.lint(&generator_scripts) {
+        eprintln!("warning: {issue}");
+    }
+
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(format!("{}-{}.zip", manifest.name, manifest.version)));
+    write_zip(&output, &files)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_pack(dir: &Path, files: &[(&str, &str)]) {
+        for (file, contents) in files {
+            let path = dir.join("data").join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_same_files_give_same_zip() {
+        let files = [
+            ("names.ron", "{}"),
+            ("scripts/default_format_flavor_description_line.glu", "\"\""),
+            ("ancestries.ron", "{}"),
+            ("notes.txt", "skipped"),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let mut zips = Vec::new();
+        for (index, order) in [[0, 1, 2, 3], [3, 2, 1, 0]].into_iter().enumerate() {
+            let input = dir.path().join(format!("homebrew{index}")).join("homebrew");
+            write_pack(&input, &order.map(|x| files[x]));
+            let (_, manifest, files) = read_pack_files(&input).unwrap();
+            assert_eq!("homebrew", manifest.name);
+            assert_eq!(
+                vec![
+                    "ancestries.ron",
+                    "names.ron",
+                    "scripts/default_format_flavor_description_line.glu",
+                ],
+                manifest.checksums.keys().collect::<Vec<_>>()
+            );
+            assert_eq!(Some(&checksum(b"{}")), manifest.checksums.get("names.ron"));
+            assert!(files.contains_key(MANIFEST_FILE));
+
+            let zip_path = dir.path().join(format!("homebrew{index}.zip"));
+            write_zip(&zip_path, &files).unwrap();
+            zips.push(fs::read(zip_path).unwrap());
+        }
+        assert_eq!(zips[0], zips[1]);
+    }
+}
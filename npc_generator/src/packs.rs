@@ -0,0 +1,416 @@
+//! Data packs installed into the data directory, one directory per pack.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use npc_generator_core::{
+    checksum,
+    loader::{self, DataSource, DirectorySource, ZipSource},
+    ManifestError, PackManifest, MANIFEST_FILE,
+};
+
+/// File in the packs directory holding the name of the active pack.
+const ACTIVE_PACK_FILE: &str = "active";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InstallMode {
+    /// Fails if a pack of the same name is installed
+    Install,
+    /// Fails unless a pack of the same name is installed
+    Update,
+    /// Installs the pack, replacing an installed one of the same name
+    Replace,
+}
+
+pub struct InstalledPack {
+    /// Name of the directory the pack is installed in
+    pub name: String,
+    pub path: PathBuf,
+    pub manifest: PackManifest,
+}
+
+pub fn packs_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|mut path| {
+        path.push("pf2e_npc_generator");
+        path.push("packs");
+        path
+    })
+}
+
+/// The data directory of a pack directory, its `data` directory if it has one
+/// and the directory itself otherwise.
+pub fn data_directory(path: &Path) -> PathBuf {
+    let data_path = path.join("data");
+    if data_path.is_dir() {
+        data_path
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn require_packs_path() -> Result<PathBuf> {
+    packs_path().ok_or_else(|| anyhow!("No data directory on this system"))
+}
+
+fn read_installed_manifest(name: &str, path: &Path) -> PackManifest {
+    let mut source = DirectorySource::new(path.join("data"));
+    match source.read(MANIFEST_FILE) {
+        Ok(Some(_)) => loader::read_manifest(&mut source).unwrap_or_else(|err| {
+            warn!("Can't read the manifest of installed pack `{name}`: {err}");
+            PackManifest::legacy(name)
+        }),
+        _ => PackManifest::legacy(name),
+    }
+}
+
+/// Lists the installed packs, sorted by name.
+pub fn installed_packs() -> Result<Vec<InstalledPack>> {
+    let Some(packs_path) = packs_path() else {
+        return Ok(Vec::new());
+    };
+    if !packs_path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut packs = Vec::new();
+    for entry in packs_path.read_dir()? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Hidden directories are installations in progress
+        if !entry.file_type()?.is_dir() || name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        packs.push(InstalledPack {
+            manifest: read_installed_manifest(&name, &path),
+            name,
+            path,
+        });
+    }
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packs)
+}
+
+fn installed_pack_path(packs_path: &Path, name: &str) -> Result<PathBuf> {
+    let path = packs_path.join(name);
+    if is_valid_pack_name(name) && path.is_dir() {
+        Ok(path)
+    } else {
+        Err(anyhow!("Pack `{name}` isn't installed"))
+    }
+}
+
+/// Pack names are directory names next to the active pack file.
+fn is_valid_pack_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && name != ACTIVE_PACK_FILE
+}
+
+pub fn active_pack_name() -> Result<Option<String>> {
+    match packs_path() {
+        Some(packs_path) => read_active_pack_name(&packs_path),
+        None => Ok(None),
+    }
+}
+
+fn read_active_pack_name(packs_path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(packs_path.join(ACTIVE_PACK_FILE)) {
+        Ok(name) => Ok(Some(name.trim().to_string()).filter(|x| !x.is_empty())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The directory of the active pack, if one is set and still installed.
+pub fn active_pack_path() -> Result<Option<PathBuf>> {
+    let Some(packs_path) = packs_path() else {
+        return Ok(None);
+    };
+    read_active_pack_name(&packs_path)?
+        .map(|name| installed_pack_path(&packs_path, &name))
+        .transpose()
+}
+
+/// Makes the installed pack the one used when no `--data` or `--pack` is given.
+pub fn use_pack(name: &str) -> Result<()> {
+    use_pack_in(&require_packs_path()?, name)
+}
+
+fn use_pack_in(packs_path: &Path, name: &str) -> Result<()> {
+    installed_pack_path(packs_path, name)?;
+    fs::write(packs_path.join(ACTIVE_PACK_FILE), name)?;
+    Ok(())
+}
+
+pub fn remove_pack(name: &str) -> Result<()> {
+    remove_pack_in(&require_packs_path()?, name)
+}
+
+fn remove_pack_in(packs_path: &Path, name: &str) -> Result<()> {
+    let path = installed_pack_path(packs_path, name)?;
+    fs::remove_dir_all(&path).with_context(|| format!("Can't remove {path:?}"))?;
+    if read_active_pack_name(packs_path)?.as_deref() == Some(name) {
+        fs::remove_file(packs_path.join(ACTIVE_PACK_FILE))?;
+    }
+    Ok(())
+}
+
+/// Extracts the zip into `target`, checking every file against the checksums
+/// of the manifest before and against the archive after extracting.
+fn extract_verified(zip_path: &Path, manifest: &PackManifest, target: &Path) -> Result<()> {
+    let file = fs::File::open(zip_path).with_context(|| format!("Can't open {zip_path:?}"))?;
+    let mut archive = zip::ZipArchive::new(file)?;
+
+    let mut checksums = BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if !file.is_file() {
+            continue;
+        }
+        let name = file.name().to_string();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        manifest.verify_checksum(&name, &contents)?;
+        checksums.insert(name, checksum(&contents));
+    }
+    if let Some(file) = manifest
+        .checksums
+        .keys()
+        .find(|x| !checksums.contains_key(*x))
+    {
+        return Err(ManifestError::MissingChecksummedFile {
+            pack: manifest.name.clone(),
+            file: file.clone(),
+        }
+        .into());
+    }
+
+    fs::create_dir_all(target)?;
+    archive
+        .extract(target)
+        .with_context(|| format!("Can't extract {zip_path:?} into {target:?}"))?;
+    for (file, expected) in &checksums {
+        let contents = fs::read(target.join(file))
+            .with_context(|| format!("Can't read extracted file {file}"))?;
+        if checksum(&contents) != *expected {
+            return Err(anyhow!("{file} is corrupted after extracting"));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the data pack zip and installs it into its own directory, named
+/// after the pack. The first installed pack becomes the active one.
+pub fn install_pack(zip_path: &Path, mode: InstallMode) -> Result<InstalledPack> {
+    let packs_path = require_packs_path()?;
+    let mut source = ZipSource::open(zip_path)?;
+    let manifest = match source.read(MANIFEST_FILE)? {
+        Some(_) => loader::read_manifest(&mut source)?,
+        None => PackManifest::legacy(
+            zip_path
+                .file_stem()
+                .map(|x| x.to_string_lossy())
+                .unwrap_or_default(),
+        ),
+    };
+    let name = manifest.name.clone();
+    if !is_valid_pack_name(&name) {
+        return Err(anyhow!("Can't install a pack named `{name}`"));
+    }
+    loader::load_generator_data(&mut source).with_context(|| format!("Can't load {zip_path:?}"))?;
+    install_checked_pack(&packs_path, zip_path, manifest, mode)
+}
+
+/// Installs a zip whose data was already checked, with `manifest` read from it.
+fn install_checked_pack(
+    packs_path: &Path,
+    zip_path: &Path,
+    manifest: PackManifest,
+    mode: InstallMode,
+) -> Result<InstalledPack> {
+    let name = manifest.name.clone();
+    let path = packs_path.join(&name);
+    match (mode, path.exists()) {
+        (InstallMode::Install, true) => {
+            return Err(anyhow!(
+                "Pack `{name}` is already installed, use update mode to replace it"
+            ))
+        }
+        (InstallMode::Update, false) => return Err(anyhow!("Pack `{name}` isn't installed")),
+        _ => {}
+    }
+
+    // Extracting next to the pack first, so a failure keeps the installed version
+    let staging_path = packs_path.join(format!(".{name}"));
+    if staging_path.exists() {
+        fs::remove_dir_all(&staging_path)?;
+    }
+    if let Err(err) = extract_verified(zip_path, &manifest, &staging_path.join("data")) {
+        if let Err(x) = fs::remove_dir_all(&staging_path) {
+            warn!("Can't clean up {staging_path:?}: {x}");
+        }
+        return Err(err);
+    }
+    if path.exists() {
+        fs::remove_dir_all(&path).with_context(|| format!("Can't remove {path:?}"))?;
+    }
+    fs::rename(&staging_path, &path)?;
+    info!("Installed {zip_path:?} into {path:?}");
+
+    if read_active_pack_name(packs_path)?.is_none() {
+        use_pack_in(packs_path, &name)?;
+    }
+    Ok(InstalledPack {
+        name,
+        path,
+        manifest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn write_zip(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, contents) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn read_names(packs_path: &Path) -> String {
+        fs::read_to_string(packs_path.join("core/data/names.ron")).unwrap()
+    }
+
+    #[test]
+    fn test_data_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let pack = dir.path().join("homebrew");
+        fs::create_dir_all(&pack).unwrap();
+        assert_eq!(pack, data_directory(&pack));
+        fs::create_dir(pack.join("data")).unwrap();
+        assert_eq!(pack.join("data"), data_directory(&pack));
+        assert_eq!(pack.join("data"), data_directory(&pack.join("data")));
+    }
+
+    #[test]
+    fn test_install_update_use_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let packs_path = dir.path().join("packs");
+        let zip_path = dir.path().join("core.zip");
+        let manifest = PackManifest::legacy("core");
+
+        write_zip(&zip_path, &[("names.ron", "first")]);
+        let pack = install_checked_pack(
+            &packs_path,
+            &zip_path,
+            manifest.clone(),
+            InstallMode::Install,
+        )
+        .unwrap();
+        assert_eq!(packs_path.join("core"), pack.path);
+        assert_eq!("first", read_names(&packs_path));
+        assert_eq!(
+            Some("core".to_string()),
+            read_active_pack_name(&packs_path).unwrap()
+        );
+        assert!(install_checked_pack(
+            &packs_path,
+            &zip_path,
+            manifest.clone(),
+            InstallMode::Install
+        )
+        .is_err());
+
+        write_zip(&zip_path, &[("names.ron", "second")]);
+        install_checked_pack(
+            &packs_path,
+            &zip_path,
+            manifest.clone(),
+            InstallMode::Update,
+        )
+        .unwrap();
+        assert_eq!("second", read_names(&packs_path));
+        let other = PackManifest::legacy("other");
+        assert!(
+            install_checked_pack(&packs_path, &zip_path, other.clone(), InstallMode::Update)
+                .is_err()
+        );
+
+        install_checked_pack(&packs_path, &zip_path, other, InstallMode::Replace).unwrap();
+        assert_eq!(
+            Some("core".to_string()),
+            read_active_pack_name(&packs_path).unwrap()
+        );
+        use_pack_in(&packs_path, "other").unwrap();
+        assert_eq!(
+            Some("other".to_string()),
+            read_active_pack_name(&packs_path).unwrap()
+        );
+        assert!(use_pack_in(&packs_path, "missing").is_err());
+        assert!(use_pack_in(&packs_path, "active").is_err());
+
+        remove_pack_in(&packs_path, "core").unwrap();
+        assert!(!packs_path.join("core").exists());
+        assert_eq!(
+            Some("other".to_string()),
+            read_active_pack_name(&packs_path).unwrap()
+        );
+        remove_pack_in(&packs_path, "other").unwrap();
+        assert_eq!(None, read_active_pack_name(&packs_path).unwrap());
+        assert!(remove_pack_in(&packs_path, "other").is_err());
+    }
+
+    #[test]
+    fn test_failed_update_keeps_installed_pack() {
+        let dir = tempfile::tempdir().unwrap();
+        let packs_path = dir.path().join("packs");
+        let zip_path = dir.path().join("core.zip");
+        let mut manifest = PackManifest::legacy("core");
+
+        write_zip(&zip_path, &[("names.ron", "first")]);
+        install_checked_pack(
+            &packs_path,
+            &zip_path,
+            manifest.clone(),
+            InstallMode::Install,
+        )
+        .unwrap();
+
+        // Fails while extracting, after the staging directory was created
+        write_zip(&zip_path, &[("names.ron", "second"), ("../escape.ron", "")]);
+        assert!(install_checked_pack(
+            &packs_path,
+            &zip_path,
+            manifest.clone(),
+            InstallMode::Update
+        )
+        .is_err());
+        assert_eq!("first", read_names(&packs_path));
+        assert!(!packs_path.join(".core").exists());
+        assert!(!dir.path().join("escape.ron").exists());
+
+        // Fails the checksums before extracting
+        write_zip(&zip_path, &[("names.ron", "second")]);
+        manifest
+            .checksums
+            .insert("names.ron".to_string(), checksum(b"first"));
+        assert!(
+            install_checked_pack(&packs_path, &zip_path, manifest, InstallMode::Update).is_err()
+        );
+        assert_eq!("first", read_names(&packs_path));
+        assert!(!packs_path.join(".core").exists());
+    }
+}
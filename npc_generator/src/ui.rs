@@ -1,4 +1,7 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, fs, sync::Arc};
+
+use log::error;
+use native_dialog::FileDialog;
 
 use npc_generator_core::{
     generators::{Generator, GeneratorData, GeneratorScripts},
@@ -6,6 +9,9 @@ use npc_generator_core::{
     NamedElement, NpcOptions, Statblock,
 };
 use rand::SeedableRng;
+
+use crate::config::Config;
+use crate::export::{write_pdf_cards, ExportFormat, UserTemplate};
 mod generator_format;
 use generator_format::*;
 mod ui_data;
@@ -15,7 +21,9 @@ pub struct UserInterface {
     generator: Generator<rand::rngs::StdRng>,
     data: UIData,
     resulting_statblock: Option<Statblock>,
+    generation_error: Option<String>,
     weight_presets: Arc<[Arc<WeightPreset>]>,
+    user_templates: Arc<[Arc<UserTemplate>]>,
 }
 
 impl UserInterface {
@@ -25,15 +33,36 @@ impl UserInterface {
         generator_data: Arc<GeneratorData>,
         generator_scripts: Arc<GeneratorScripts>,
         weight_presets: impl AsRef<[Arc<WeightPreset>]>,
+        user_templates: impl AsRef<[Arc<UserTemplate>]>,
+        config: &Config,
     ) -> Self {
         // This is also where you can customize the look and feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
+        let archetype = config.level.and_then(|level| {
+            generator_data
+                .archetypes
+                .iter()
+                .find(|x| x.level() == level)
+                .cloned()
+        });
         let data: UIData = UIData {
+            generated_text_format: match config.format {
+                ExportFormat::Pf2eStats => GeneratorFormat::PF2EStats,
+                _ => GeneratorFormat::Flavor,
+            },
+            use_archetype: archetype.is_some(),
             npc_options: NpcOptions {
-                enable_flavor_text: true,
+                archetype,
+                enable_flavor_text: config.flavor.unwrap_or(true),
+                units: config.units,
                 ..Default::default()
             },
+            current_weight_preset: weight_presets
+                .as_ref()
+                .iter()
+                .find(|x| config.preset.as_deref() == Some(&x.name()))
+                .cloned(),
             ..Default::default()
         };
 
@@ -46,7 +75,9 @@ impl UserInterface {
             )
             .unwrap(),
             resulting_statblock: Default::default(),
+            generation_error: Default::default(),
             weight_presets: weight_presets.as_ref().into(),
+            user_templates: user_templates.as_ref().into(),
         }
     }
 
@@ -81,15 +112,48 @@ impl UserInterface {
             )
             .clicked()
         {
-            self.resulting_statblock = match self.generator.generate(
+            match self.generator.generate(
                 &self.data.npc_options,
                 self.data.current_weight_preset.clone(),
             ) {
-                Ok(x) => Some(x),
-                Err(_err) => {
-                    // todo pop-up error dialog
-                    None
+                Ok(statblock) => {
+                    self.resulting_statblock = Some(statblock);
+                    self.generation_error = None;
+                }
+                Err(err) => {
+                    error!("Couldn't generate NPC: {err}");
+                    self.resulting_statblock = None;
+                    self.generation_error = Some(err.to_string());
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn ui_save_pdf_button(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .add_enabled(
+                self.resulting_statblock.is_some(),
+                egui::Button::new("Save as PDF"),
+            )
+            .clicked()
+        {
+            let Some(ref statblock) = self.resulting_statblock else {
+                return;
+            };
+            match FileDialog::new()
+                .add_filter("PDF Document", &["pdf"])
+                .show_save_single_file()
+            {
+                Ok(Some(path)) => {
+                    if let Err(err) =
+                        fs::write(&path, write_pdf_cards(std::slice::from_ref(statblock)))
+                    {
+                        error!("Couldn't write {path:?}: {err}");
+                    }
                 }
+                Ok(None) => {}
+                Err(err) => error!("Couldn't open save dialog: {err}"),
             }
         }
     }
@@ -109,6 +173,13 @@ impl UserInterface {
                     GeneratorFormat::PF2EStats,
                     GeneratorFormat::PF2EStats.to_string(),
                 );
+                for template in self.user_templates.iter() {
+                    ui.selectable_value(
+                        &mut self.data.generated_text_format,
+                        GeneratorFormat::Template(template.name().to_string()),
+                        template.name(),
+                    );
+                }
             });
     }
 
@@ -222,7 +293,7 @@ impl UserInterface {
                     Some(None),
                     "Normal Person",
                 );
-                for heritage in &self.generator.data.versitile_heritages {
+                for heritage in &self.generator.data.versatile_heritages {
                     ui.selectable_value(
                         &mut self.data.npc_options.heritage,
                         Some(Some(heritage.0.clone())),
@@ -353,12 +424,23 @@ impl UserInterface {
 
     #[inline]
     fn ui_generated_text_textedit(&mut self, ui: &mut egui::Ui) {
+        if let Some(ref generation_error) = self.generation_error {
+            ui.colored_label(ui.visuals().error_fg_color, generation_error);
+        }
         if let Some(ref resulting_statblock) = self.resulting_statblock {
             ui.add_sized(
                 egui::vec2(ui.available_width(), ui.available_height()),
-                egui::TextEdit::multiline(&mut match self.data.generated_text_format {
+                egui::TextEdit::multiline(&mut match &self.data.generated_text_format {
                     GeneratorFormat::Flavor => resulting_statblock.flavor().to_string(),
                     GeneratorFormat::PF2EStats => resulting_statblock.as_pf2e_stats().to_string(),
+                    GeneratorFormat::Template(name) => {
+                        match self.user_templates.iter().find(|x| x.name() == name) {
+                            Some(template) => template
+                                .render(resulting_statblock)
+                                .unwrap_or_else(|err| format!("{err:#}")),
+                            None => format!("Template '{name}' doesn't exist"),
+                        }
+                    }
                 }),
             );
         }
@@ -372,6 +454,7 @@ impl UserInterface {
 
             ui.horizontal(|ui| {
                 self.ui_generate_button(ui);
+                self.ui_save_pdf_button(ui);
                 self.ui_generator_mode_combobox(ui);
                 self.ui_use_archetype_checkbox(ui);
                 self.ui_weight_preset_combobox(ui);
@@ -402,7 +485,7 @@ impl UserInterface {
     }
 }
 impl eframe::App for UserInterface {
-    /// Called by the frame work to save state before shutdown.
+    // Called by the frame work to save state before shutdown.
     //fn save(&mut self, storage: &mut dyn eframe::Storage) {}
 
     /// Called each time the UI needs repainting, which may be many times per second.
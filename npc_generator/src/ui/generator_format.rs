@@ -2,10 +2,13 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum GeneratorFormat {
+    #[default]
     Flavor,
     PF2EStats,
+    /// A user template, referenced by its name
+    Template(String),
 }
 
 impl Display for GeneratorFormat {
@@ -16,13 +19,8 @@ impl Display for GeneratorFormat {
             match self {
                 GeneratorFormat::Flavor => "Flavor",
                 GeneratorFormat::PF2EStats => "pf2e-stats",
+                GeneratorFormat::Template(name) => name,
             }
         )
     }
 }
-
-impl Default for GeneratorFormat {
-    fn default() -> Self {
-        Self::Flavor
-    }
-}
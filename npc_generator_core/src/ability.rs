@@ -61,7 +61,7 @@ impl AbilityModifications {
         Self(value.into())
     }
 
-    pub fn iter(&self) -> core::slice::Iter<AbilityBoost> {
+    pub fn iter(&self) -> core::slice::Iter<'_, AbilityBoost> {
         self.0.iter()
     }
 }
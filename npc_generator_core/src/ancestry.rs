@@ -10,8 +10,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::formats::Formats;
 use crate::{
-    AbilityModifications, AgeRange, AgeRanges, Language, Mutation, NamedElement, Sense, Size,
-    Trait, WeightMap,
+    AbilityModifications, AgeRange, AgeRanges, BodyMeasurements, FaceFeatures, Language, Mutation,
+    NamedElement, Sense, Size, Trait, WeightMap,
 };
 
 type AncestryString = Arc<str>;
@@ -44,6 +44,10 @@ pub struct Ancestry {
     skin_substance: AncestryString,
     base_hp: u8,
     #[serde(default)]
+    face: FaceFeatures,
+    #[serde(default)]
+    body: Option<BodyMeasurements>,
+    #[serde(default)]
     formats: Formats,
 }
 lazy_static! {
@@ -81,6 +85,8 @@ impl Ancestry {
         hair_substance: impl AsRef<str>,
         skin_substance: impl AsRef<str>,
         base_hp: u8,
+        face: FaceFeatures,
+        body: Option<BodyMeasurements>,
         formats: Formats,
     ) -> Self {
         Self {
@@ -106,6 +112,8 @@ impl Ancestry {
             possible_skin_tone: possible_skin_tone.into(),
             possible_skin_texture: possible_skin_texture.into(),
             base_hp,
+            face,
+            body,
             formats,
         }
     }
@@ -188,6 +196,14 @@ impl Ancestry {
         self.base_hp
     }
 
+    pub fn face(&self) -> &FaceFeatures {
+        &self.face
+    }
+
+    pub fn body(&self) -> Option<&BodyMeasurements> {
+        self.body.as_ref()
+    }
+
     pub fn formats(&self) -> &Formats {
         &self.formats
     }
@@ -197,10 +213,10 @@ impl NamedElement for Ancestry {
     fn traits(&self) -> &[Trait] {
         &self.traits
     }
-    fn name(&self) -> Cow<str> {
+    fn name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
-    fn formatted_name(&self) -> Cow<str> {
+    fn formatted_name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
 }
@@ -27,7 +27,7 @@ pub struct Archetype {
 }
 
 impl Archetype {
-    pub fn name(&self) -> Cow<str> {
+    pub fn name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
 
@@ -51,7 +51,7 @@ impl Archetype {
         &self.attributes
     }
 
-    pub fn items_iter(&self) -> impl std::iter::Iterator<Item = Cow<str>> {
+    pub fn items_iter(&self) -> impl std::iter::Iterator<Item = Cow<'_, str>> {
         self.items.iter().map(|x| Cow::Borrowed(x.as_str()))
     }
 
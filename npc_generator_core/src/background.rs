@@ -30,11 +30,11 @@ impl NamedElement for Background {
     fn traits(&self) -> &[Trait] {
         &self.traits
     }
-    fn name(&self) -> Cow<str> {
+    fn name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
 
-    fn formatted_name(&self) -> Cow<str> {
+    fn formatted_name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
 }
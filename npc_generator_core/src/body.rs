@@ -0,0 +1,123 @@
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{AgeRange, DiceExpression, WeightMap};
+
+/// The units heights and weights are shown in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Units {
+    /// Feet, inches and pounds
+    #[default]
+    Imperial,
+    /// Meters and kilograms
+    Metric,
+}
+
+impl Units {
+    pub fn format_height(&self, inches: f64) -> String {
+        match self {
+            Units::Imperial => {
+                let inches = inches.round() as u32;
+                format!("{} ft {} in", inches / 12, inches % 12)
+            }
+            Units::Metric => format!("{:.2} m", inches * 0.0254),
+        }
+    }
+
+    pub fn format_weight(&self, pounds: f64) -> String {
+        match self {
+            Units::Imperial => format!("{} lb", pounds.round()),
+            Units::Metric => format!("{} kg", (pounds * 0.453_592).round()),
+        }
+    }
+}
+
+impl Display for Units {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Units::Imperial => "imperial",
+                Units::Metric => "metric",
+            }
+        )
+    }
+}
+
+impl FromStr for Units {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "imperial" => Ok(Units::Imperial),
+            "metric" => Ok(Units::Metric),
+            _ => Err(format!("unknown units `{s}`, use imperial or metric")),
+        }
+    }
+}
+
+/// How tall and heavy the adults of an ancestry are.
+///
+/// The height is `base_height` plus the roll of `height_modifier` in inches, the
+/// weight is `base_weight` plus the height roll times the roll of
+/// `weight_modifier` in pounds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BodyMeasurements {
+    pub base_height: u16,
+    pub height_modifier: DiceExpression,
+    pub base_weight: u16,
+    pub weight_modifier: DiceExpression,
+    /// Descriptions of the build, like `"stocky"`.
+    #[serde(default)]
+    pub builds: WeightMap<Arc<str>>,
+}
+
+impl BodyMeasurements {
+    /// Height in inches and weight in pounds of an NPC of that age range.
+    /// Infants, children and youths are scaled down from the adult measurements.
+    pub fn roll(&self, rng: &mut impl Rng, age_range: AgeRange) -> (f64, f64) {
+        let height_roll = self.height_modifier.roll(rng);
+        let weight_roll = self.weight_modifier.roll(rng);
+        let height = f64::from(self.base_height) + height_roll as f64;
+        let weight = f64::from(self.base_weight) + (height_roll * weight_roll) as f64;
+        let growth = match age_range {
+            AgeRange::Infant => 0.35,
+            AgeRange::Child => 0.6,
+            AgeRange::Youth => 0.9,
+            _ => 1.0,
+        };
+        (height * growth, weight * growth * growth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_roll_and_format() {
+        let body = BodyMeasurements {
+            base_height: 60,
+            height_modifier: "1d4".parse().unwrap(),
+            base_weight: 100,
+            weight_modifier: "1d4".parse().unwrap(),
+            builds: WeightMap::new(),
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let (height, weight) = body.roll(&mut rng, AgeRange::Adult);
+        assert!((61.0..=64.0).contains(&height));
+        assert!((101.0..=116.0).contains(&weight));
+        let (height, _) = body.roll(&mut rng, AgeRange::Child);
+        assert!(height < 40.0);
+
+        assert_eq!("5 ft 9 in", Units::Imperial.format_height(69.0));
+        assert_eq!("1.75 m", Units::Metric.format_height(69.0));
+        assert_eq!("172 lb", Units::Imperial.format_weight(172.0));
+        assert_eq!("78 kg", Units::Metric.format_weight(172.0));
+        assert_eq!(Ok(Units::Metric), "Metric".parse());
+    }
+}
@@ -1,5 +1,8 @@
+use std::{fmt::Display, str::FromStr};
+
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
 pub struct DiceFormula(pub i8, pub Die);
@@ -24,6 +27,29 @@ pub enum Die {
 }
 
 impl Die {
+    pub fn from_sides(sides: u8) -> Option<Self> {
+        match sides {
+            4 => Some(Die::D4),
+            6 => Some(Die::D6),
+            8 => Some(Die::D8),
+            10 => Some(Die::D10),
+            12 => Some(Die::D12),
+            20 => Some(Die::D20),
+            _ => None,
+        }
+    }
+
+    pub fn sides(&self) -> u8 {
+        match self {
+            Die::D4 => 4,
+            Die::D6 => 6,
+            Die::D8 => 8,
+            Die::D10 => 10,
+            Die::D12 => 12,
+            Die::D20 => 20,
+        }
+    }
+
     pub fn roll(&self, rng: &mut impl Rng) -> i8 {
         rng.gen_range(match self {
             Die::D4 => 1..=4,
@@ -35,3 +61,406 @@ impl Die {
         })
     }
 }
+
+/// The most dice a single term of a [`DiceExpression`] can roll.
+pub const MAX_DICE: u32 = 1000;
+/// The most sides a die of a [`DiceExpression`] can have.
+pub const MAX_SIDES: u32 = 1000;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DiceParseError {
+    #[error("the dice expression is empty")]
+    Empty,
+    #[error("`{0}` is neither dice like `2d6` nor a number")]
+    InvalidTerm(String),
+    #[error("`{0}` keeps more dice than it rolls")]
+    KeepsTooMany(String),
+    #[error("`{0}` rolls more than {MAX_DICE} dice")]
+    TooManyDice(String),
+    #[error("`{0}` rolls dice with more than {MAX_SIDES} sides")]
+    TooManySides(String),
+}
+
+/// A part of a [`DiceExpression`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiceTerm {
+    /// `count` dice with `sides` sides, of which only the `keep_highest` highest
+    /// count if given, subtracted if `negative`.
+    Dice {
+        negative: bool,
+        count: u32,
+        sides: u32,
+        keep_highest: Option<u32>,
+    },
+    Constant(i64),
+}
+
+impl DiceTerm {
+    fn kept(count: u32, keep_highest: Option<u32>) -> i64 {
+        keep_highest.unwrap_or(count).min(count).into()
+    }
+
+    fn sign(negative: bool) -> i64 {
+        if negative {
+            -1
+        } else {
+            1
+        }
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> i64 {
+        match *self {
+            DiceTerm::Dice {
+                negative,
+                count,
+                sides,
+                keep_highest,
+            } => {
+                let mut rolls = (0..count)
+                    .map(|_| rng.gen_range(1..=i64::from(sides)))
+                    .collect::<Vec<_>>();
+                rolls.sort_unstable_by(|a, b| b.cmp(a));
+                let kept = Self::kept(count, keep_highest) as usize;
+                Self::sign(negative) * rolls[..kept].iter().sum::<i64>()
+            }
+            DiceTerm::Constant(x) => x,
+        }
+    }
+
+    fn bounds(&self) -> (i64, i64) {
+        match *self {
+            DiceTerm::Dice {
+                negative,
+                count,
+                sides,
+                keep_highest,
+            } => {
+                let kept = Self::kept(count, keep_highest);
+                let (low, high) = (kept, kept * i64::from(sides));
+                if negative {
+                    (-high, -low)
+                } else {
+                    (low, high)
+                }
+            }
+            DiceTerm::Constant(x) => (x, x),
+        }
+    }
+
+    pub fn mean(&self) -> f64 {
+        match *self {
+            DiceTerm::Dice {
+                negative,
+                count,
+                sides,
+                keep_highest,
+            } => {
+                let kept = Self::kept(count, keep_highest) as u32;
+                let sides = f64::from(sides);
+                let mean = if kept == count {
+                    f64::from(count) * (sides + 1.0) / 2.0
+                } else {
+                    // The kept dice add one for every value v up to theirs, so the mean
+                    // is the sum over every value v of how many kept dice show v or more
+                    (1..=sides as u32)
+                        .map(|value| {
+                            let p = (sides - f64::from(value) + 1.0) / sides;
+                            expected_kept(count, kept, p)
+                        })
+                        .sum()
+                };
+                Self::sign(negative) as f64 * mean
+            }
+            DiceTerm::Constant(x) => x as f64,
+        }
+    }
+}
+
+/// The mean of the number of successes in `n` tries with chance `p` each,
+/// counting at most `k` of them.
+fn expected_kept(n: u32, k: u32, p: f64) -> f64 {
+    let mut binomial = 1.0;
+    let mut result = 0.0;
+    for successes in 1..=n {
+        binomial *= f64::from(n - successes + 1) / f64::from(successes);
+        result += f64::from(successes.min(k))
+            * binomial
+            * p.powi(successes as i32)
+            * (1.0 - p).powi((n - successes) as i32);
+    }
+    result
+}
+
+impl Display for DiceTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiceTerm::Dice {
+                count,
+                sides,
+                keep_highest,
+                ..
+            } => {
+                write!(f, "{count}d{sides}")?;
+                if let Some(keep) = keep_highest {
+                    write!(f, "kh{keep}")?;
+                }
+                Ok(())
+            }
+            DiceTerm::Constant(x) => write!(f, "{}", x.abs()),
+        }
+    }
+}
+
+impl FromStr for DiceTerm {
+    type Err = DiceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DiceParseError::InvalidTerm(s.to_string());
+        let number = |x: &str| {
+            if x.bytes().all(|x| x.is_ascii_digit()) {
+                x.parse::<u32>().map_err(|_| invalid())
+            } else {
+                Err(invalid())
+            }
+        };
+        let Some((count, rest)) = s.split_once(['d', 'D']) else {
+            return s
+                .parse::<u64>()
+                .ok()
+                .and_then(|x| i64::try_from(x).ok())
+                .map(DiceTerm::Constant)
+                .filter(|_| s.bytes().all(|x| x.is_ascii_digit()))
+                .ok_or_else(invalid);
+        };
+        let count = if count.is_empty() { 1 } else { number(count)? };
+        let (sides, keep_highest) = match rest.split_once("kh").or_else(|| rest.split_once('k')) {
+            Some((sides, keep)) => (number(sides)?, Some(number(keep)?)),
+            None => (number(rest)?, None),
+        };
+        if count == 0 || sides == 0 || keep_highest == Some(0) {
+            return Err(invalid());
+        }
+        if count > MAX_DICE {
+            return Err(DiceParseError::TooManyDice(s.to_string()));
+        }
+        if sides > MAX_SIDES {
+            return Err(DiceParseError::TooManySides(s.to_string()));
+        }
+        if keep_highest.is_some_and(|x| x > count) {
+            return Err(DiceParseError::KeepsTooMany(s.to_string()));
+        }
+        Ok(DiceTerm::Dice {
+            negative: false,
+            count,
+            sides,
+            keep_highest,
+        })
+    }
+}
+
+/// A sum of dice and constants like `2d8+4` or `4d6kh3 - 1d4`, written as a
+/// string in data files.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DiceExpression(pub Vec<DiceTerm>);
+
+impl DiceExpression {
+    /// The constants can be as big as an `i64`, so the sums saturate.
+    pub fn roll(&self, rng: &mut impl Rng) -> i64 {
+        self.0
+            .iter()
+            .map(|x| x.roll(rng))
+            .fold(0, i64::saturating_add)
+    }
+
+    pub fn min(&self) -> i64 {
+        self.0
+            .iter()
+            .map(|x| x.bounds().0)
+            .fold(0, i64::saturating_add)
+    }
+
+    pub fn max(&self) -> i64 {
+        self.0
+            .iter()
+            .map(|x| x.bounds().1)
+            .fold(0, i64::saturating_add)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.0.iter().map(DiceTerm::mean).sum()
+    }
+}
+
+impl From<DiceFormula> for DiceExpression {
+    fn from(value: DiceFormula) -> Self {
+        DiceExpression(vec![DiceTerm::Dice {
+            negative: false,
+            count: value.0.max(0) as u32,
+            sides: value.1.sides().into(),
+            keep_highest: None,
+        }])
+    }
+}
+
+impl Display for DiceExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "0");
+        }
+        for (index, term) in self.0.iter().enumerate() {
+            let negative = match term {
+                DiceTerm::Dice { negative, .. } => *negative,
+                DiceTerm::Constant(x) => *x < 0,
+            };
+            match (index, negative) {
+                (0, false) => {}
+                (_, true) => write!(f, "-")?,
+                (_, false) => write!(f, "+")?,
+            }
+            write!(f, "{term}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DiceExpression {
+    type Err = DiceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.split_whitespace().collect::<String>();
+        if text.is_empty() {
+            return Err(DiceParseError::Empty);
+        }
+        let mut terms = Vec::new();
+        let mut rest = text.as_str();
+        let mut negative = false;
+        if let Some(x) = rest.strip_prefix('-') {
+            negative = true;
+            rest = x;
+        }
+        loop {
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            let term = match rest[..end].parse()? {
+                DiceTerm::Constant(x) if negative => DiceTerm::Constant(-x),
+                DiceTerm::Dice {
+                    count,
+                    sides,
+                    keep_highest,
+                    ..
+                } => DiceTerm::Dice {
+                    negative,
+                    count,
+                    sides,
+                    keep_highest,
+                },
+                term => term,
+            };
+            terms.push(term);
+            if end == rest.len() {
+                return Ok(DiceExpression(terms));
+            }
+            negative = rest[end..].starts_with('-');
+            rest = &rest[end + 1..];
+        }
+    }
+}
+
+impl TryFrom<String> for DiceExpression {
+    type Error = DiceParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<DiceExpression> for String {
+    fn from(value: DiceExpression) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_parse_and_display() {
+        for (text, shown) in [
+            ("2d8+4", "2d8+4"),
+            ("d20", "1d20"),
+            (" 4d6kh3 - 1d4 + 2d6 - 1 ", "4d6kh3-1d4+2d6-1"),
+            ("3D100k2", "3d100kh2"),
+            ("-5+1d3", "-5+1d3"),
+            ("7", "7"),
+        ] {
+            let expression: DiceExpression = text.parse().unwrap();
+            assert_eq!(shown, expression.to_string());
+            assert_eq!(Ok(expression), shown.parse());
+        }
+        for (text, error) in [
+            ("", DiceParseError::Empty),
+            ("2d", DiceParseError::InvalidTerm("2d".into())),
+            ("2d6+", DiceParseError::InvalidTerm("".into())),
+            ("0d6", DiceParseError::InvalidTerm("0d6".into())),
+            ("2x6", DiceParseError::InvalidTerm("2x6".into())),
+            ("2d6kh3", DiceParseError::KeepsTooMany("2d6kh3".into())),
+            ("1001d6", DiceParseError::TooManyDice("1001d6".into())),
+            (
+                "1000d4000000000kh999",
+                DiceParseError::TooManySides("1000d4000000000kh999".into()),
+            ),
+            ("2d1001", DiceParseError::TooManySides("2d1001".into())),
+        ] {
+            assert_eq!(Err(error), text.parse::<DiceExpression>());
+        }
+    }
+
+    #[test]
+    fn test_statistics() {
+        let expression: DiceExpression = "2d8+4".parse().unwrap();
+        assert_eq!(
+            (6, 20, 13.0),
+            (expression.min(), expression.max(), expression.mean())
+        );
+        let expression: DiceExpression = "1d6-1d4".parse().unwrap();
+        assert_eq!(
+            (-3, 5, 1.0),
+            (expression.min(), expression.max(), expression.mean())
+        );
+        let expression: DiceExpression = "2d20kh1".parse().unwrap();
+        assert_eq!((1, 20), (expression.min(), expression.max()));
+        assert!((expression.mean() - 13.825).abs() < 1e-9);
+        let expression: DiceExpression = "4d6kh3".parse().unwrap();
+        assert!((expression.mean() - 12.244598765).abs() < 1e-6);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let roll = expression.roll(&mut rng);
+            assert!((3..=18).contains(&roll));
+        }
+        let expression: DiceExpression = "1000d1000kh999".parse().unwrap();
+        assert_eq!((999, 999_000), (expression.min(), expression.max()));
+        assert!((expression.mean() - 500_498.42).abs() < 0.01);
+        let expression: DiceExpression = "9223372036854775807+1-1d4".parse().unwrap();
+        assert_eq!(
+            (i64::MAX - 4, i64::MAX - 1),
+            (expression.min(), expression.max())
+        );
+        let expression: DiceExpression = "9223372036854775807+1".parse().unwrap();
+        assert_eq!(i64::MAX, expression.roll(&mut StdRng::seed_from_u64(0)));
+
+        assert_eq!(
+            DiceExpression::from(DiceFormula(2, Die::D10)),
+            "2d10".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_serde_as_string() {
+        let expression: DiceExpression = ron::from_str(r#""2d8 + 4""#).unwrap();
+        assert_eq!(r#""2d8+4""#, ron::to_string(&expression).unwrap());
+        assert!(ron::from_str::<DiceExpression>(r#""2d""#).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{AgeRange, WeightMap};
+
+type FaceString = Arc<str>;
+
+/// Facial hair an NPC can have, e.g. `"a braided beard"`.
+///
+/// Empty filters match every NPC.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FacialHair {
+    pub text: FaceString,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default)]
+    pub sexes: Vec<String>,
+    #[serde(default)]
+    pub age_ranges: Vec<AgeRange>,
+}
+
+impl FacialHair {
+    pub fn applies_to(&self, sex: &str, age_range: AgeRange) -> bool {
+        (self.sexes.is_empty() || self.sexes.iter().any(|x| x == sex))
+            && (self.age_ranges.is_empty() || self.age_ranges.contains(&age_range))
+    }
+}
+
+/// A feature an NPC has with the given chance, e.g. `"tusks jutting from the lower jaw"`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FacialFeature {
+    pub text: FaceString,
+    #[serde(default = "default_chance")]
+    pub chance: f64,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+fn default_chance() -> f64 {
+    1.0
+}
+
+/// The weighted facial features of an ancestry, or the ones a heritage adds to them.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct FaceFeatures {
+    #[serde(default)]
+    pub shapes: WeightMap<FaceString>,
+    #[serde(default)]
+    pub noses: WeightMap<FaceString>,
+    #[serde(default)]
+    pub ears: WeightMap<FaceString>,
+    #[serde(default)]
+    pub jaws: WeightMap<FaceString>,
+    #[serde(default)]
+    pub facial_hair: Vec<FacialHair>,
+    #[serde(default)]
+    pub features: Vec<FacialFeature>,
+}
+
+impl FaceFeatures {
+    /// The features with the ones of `additions` added, additions with the same
+    /// text replace the weight of the original.
+    pub fn with(&self, additions: &FaceFeatures) -> FaceFeatures {
+        let mut result = self.clone();
+        result
+            .shapes
+            .extend(additions.shapes.iter().map(|(k, v)| (k.clone(), *v)));
+        result
+            .noses
+            .extend(additions.noses.iter().map(|(k, v)| (k.clone(), *v)));
+        result
+            .ears
+            .extend(additions.ears.iter().map(|(k, v)| (k.clone(), *v)));
+        result
+            .jaws
+            .extend(additions.jaws.iter().map(|(k, v)| (k.clone(), *v)));
+        result
+            .facial_hair
+            .extend(additions.facial_hair.iter().cloned());
+        result.features.extend(additions.features.iter().cloned());
+        result
+    }
+
+    /// The facial hair possible for an NPC of that sex and age range.
+    pub fn facial_hair_for(&self, sex: &str, age_range: AgeRange) -> Vec<&FacialHair> {
+        self.facial_hair
+            .iter()
+            .filter(|x| x.applies_to(sex, age_range))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heritage_additions() {
+        let orc: FaceFeatures = ron::from_str(
+            r#"#![enable(unwrap_newtypes)]
+            (
+                ears: {"small": 2},
+                facial_hair: [
+                    (text: "a thick beard", sexes: ["male"], age_ranges: [Adult, Old]),
+                    (text: "a clean-shaven chin", weight: 3),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let dromaar: FaceFeatures =
+            ron::from_str(r#"#![enable(unwrap_newtypes)] (ears: {"small": 5, "pointed": 1}, features: [(text: "tusks")])"#)
+                .unwrap();
+        let face = orc.with(&dromaar);
+
+        assert_eq!(Some(&5), face.ears.get("small"));
+        assert_eq!(2, face.ears.len());
+        assert_eq!(1.0, face.features[0].chance);
+        assert_eq!(2, face.facial_hair_for("male", AgeRange::Adult).len());
+        assert_eq!(1, face.facial_hair_for("female", AgeRange::Adult).len());
+        assert_eq!(1, face.facial_hair_for("male", AgeRange::Child).len());
+    }
+}
@@ -1,9 +1,26 @@
-use std::{cell::RefCell, fmt::Display, sync::Arc, thread::LocalKey};
+use std::{
+    cell::RefCell, collections::HashMap, fmt::Display, future::Future, ops::Range, sync::Arc,
+    task::Poll, thread::LocalKey, time::Duration,
+};
 
-use gluon::ThreadExt;
+use gluon::{
+    base::types::ArcType,
+    vm::{
+        api::{typ::from_rust, Getable, OwnedFunction, Pushable, VmType},
+        thread::{ActiveThread, HookFlags, ThreadInternal},
+    },
+    Thread, ThreadExt,
+};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::AgeRange;
+use crate::{
+    generators::GeneratorScripts,
+    sandbox::{check_imports, script_limits, Budget, SandboxViolation, ScriptLimits},
+    script_random::{add_random_module, seed_script_rng, RANDOM_MODULE},
+    AgeRange, NamedElement, Statblock, Trait,
+};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct FormatString(Arc<str>);
@@ -45,9 +62,211 @@ impl From<&str> for FormatString {
     }
 }
 
+/// The flavor lines data packs can script.
+///
+/// A line's script gets the [`FlavorStatblock`] of the NPC and the line
+/// generated by default, and returns the line to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlavorLine {
+    HairAndEyes,
+    Skin,
+    SizeAndBuild,
+    Face,
+    Habit,
+}
+
+impl FlavorLine {
+    pub fn values() -> &'static [FlavorLine] {
+        &[
+            Self::HairAndEyes,
+            Self::Skin,
+            Self::SizeAndBuild,
+            Self::Face,
+            Self::Habit,
+        ]
+    }
+
+    /// Name of the script in the [`GeneratorScripts`](crate::generators::GeneratorScripts)
+    /// and in the `lines` of ancestry formats.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::HairAndEyes => "hair_and_eyes_line",
+            Self::Skin => "skin_line",
+            Self::SizeAndBuild => "size_and_build_line",
+            Self::Face => "face_line",
+            Self::Habit => "habit_line",
+        }
+    }
+
+    /// The data pack file of the script.
+    pub fn file(self) -> &'static str {
+        match self {
+            Self::HairAndEyes => "scripts/hair_and_eyes_line.glu",
+            Self::Skin => "scripts/skin_line.glu",
+            Self::SizeAndBuild => "scripts/size_and_build_line.glu",
+            Self::Face => "scripts/face_line.glu",
+            Self::Habit => "scripts/habit_line.glu",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::values().iter().copied().find(|x| x.name() == name)
+    }
+}
+
+/// The attribute modifiers of a [`FlavorStatblock`].
+#[derive(Serialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct Attributes {
+    pub strength: i64,
+    pub dexterity: i64,
+    pub constitution: i64,
+    pub intelligence: i64,
+    pub wisdom: i64,
+    pub charisma: i64,
+}
+
+/// A skill of a [`FlavorStatblock`] with its modifier.
+#[derive(Serialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct SkillModifier {
+    /// The skill as written in statblocks, e.g. `Lore (Sailing)`
+    pub skill: String,
+    pub modifier: i64,
+}
+
+/// The statblock fields scripts can read, the gluon record
+/// `npc_generator.core.FlavorStatblock`.
+///
+/// Pushed into the VM field by field, so the order of the fields has to stay
+/// the one of the type in [`CORE_RECORD_TYPES`].
+#[derive(Serialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct FlavorStatblock {
+    pub name: String,
+    pub level: i64,
+    pub age: u64,
+    pub age_range: AgeRange,
+    /// Empty for asexual ancestries
+    pub sex: String,
+    pub ancestry: String,
+    pub ancestry_traits: Vec<String>,
+    /// Empty without a heritage
+    pub heritage: String,
+    pub heritage_traits: Vec<String>,
+    pub background: String,
+    /// The archetype name when generated for an archetype, the background otherwise
+    pub class: String,
+    pub size: String,
+    /// Every trait of the NPC
+    pub traits: Vec<String>,
+    pub attributes: Attributes,
+    pub skills: Vec<SkillModifier>,
+    pub perception: i64,
+    pub armor_class: i64,
+    pub fortitude_save: i64,
+    pub reflex_save: i64,
+    pub will_save: i64,
+    pub hit_points: i64,
+    pub land_speed: i64,
+}
+
+fn trait_names(traits: &[Trait]) -> Vec<String> {
+    traits.iter().map(ToString::to_string).collect()
+}
+
+impl FlavorStatblock {
+    pub fn new(statblock: &Statblock, background: &str) -> Self {
+        let ancestry = statblock.ancestry();
+        let heritage = statblock.heritage();
+        let attributes = statblock.attributes();
+        Self {
+            name: statblock.name().to_string(),
+            level: statblock.level().into(),
+            age: statblock.age(),
+            age_range: statblock.age_range(),
+            sex: statblock.sex().to_string(),
+            ancestry: ancestry.map(|x| x.name().to_string()).unwrap_or_default(),
+            ancestry_traits: ancestry
+                .map(|x| trait_names(x.traits()))
+                .unwrap_or_default(),
+            heritage: heritage.map(|x| x.name().to_string()).unwrap_or_default(),
+            heritage_traits: heritage
+                .map(|x| trait_names(x.traits()))
+                .unwrap_or_default(),
+            background: background.to_string(),
+            class: statblock.class().to_string(),
+            size: ancestry.map(|x| x.size().to_string()).unwrap_or_default(),
+            traits: trait_names(statblock.traits()),
+            attributes: Attributes {
+                strength: attributes.strength.into(),
+                dexterity: attributes.dexterity.into(),
+                constitution: attributes.constitution.into(),
+                intelligence: attributes.intelligence.into(),
+                wisdom: attributes.wisdom.into(),
+                charisma: attributes.charisma.into(),
+            },
+            skills: statblock
+                .skills()
+                .iter()
+                .map(|(skill, modifier)| SkillModifier {
+                    skill: skill.to_string(),
+                    modifier: (*modifier).into(),
+                })
+                .collect(),
+            perception: statblock.perception().into(),
+            armor_class: statblock.armor_class().into(),
+            fortitude_save: statblock.fortitude_save().into(),
+            reflex_save: statblock.reflex_save().into(),
+            will_save: statblock.will_save().into(),
+            hit_points: statblock.hit_points().into(),
+            land_speed: statblock.land_speed().into(),
+        }
+    }
+
+    /// The arguments of the positional description line signature scripts
+    /// had before the record, with the spacing they expect.
+    fn description_line_arguments(
+        &self,
+    ) -> (String, u64, AgeRange, String, String, String, String) {
+        let prefix_space = |x: &str| {
+            if x.is_empty() {
+                String::new()
+            } else {
+                format!(" {x}")
+            }
+        };
+        (
+            self.name.clone(),
+            self.age,
+            self.age_range,
+            prefix_space(&self.sex),
+            self.ancestry.clone(),
+            prefix_space(&self.heritage),
+            self.background.clone(),
+        )
+    }
+}
+
+impl VmType for FlavorStatblock {
+    type Type = Self;
+    fn make_type(thread: &Thread) -> ArcType {
+        thread
+            .find_type_info("npc_generator.core.FlavorStatblock")
+            .unwrap()
+            .into_type()
+    }
+}
+impl<'vm> Pushable<'vm> for FlavorStatblock {
+    fn vm_push(self, context: &mut ActiveThread<'vm>) -> gluon::vm::Result<()> {
+        gluon::vm::api::ser::Ser(self).vm_push(context)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct Formats {
     pub full_name: FormatString,
+    /// Scripts of this ancestry replacing the data pack's [`FlavorLine`]
+    /// scripts, keyed by [`FlavorLine::name`].
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub lines: HashMap<String, FormatString>,
 }
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct HeritageFormats {
@@ -58,6 +277,7 @@ impl Default for Formats {
         Self {
             full_name: r#"\first_name surname additional_names -> first_name ++ " " ++ surname"#
                 .into(),
+            lines: HashMap::new(),
         }
     }
 }
@@ -70,94 +290,458 @@ impl Default for HeritageFormats {
     }
 }
 
+/// A gluon format script that failed to compile or run.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("error in format script `{script}`: {diagnostic}")]
+pub struct FormatError {
+    /// Name of the failing script, e.g. `full_name`.
+    pub script: String,
+    /// The diagnostic reported by gluon.
+    pub diagnostic: String,
+    /// Byte range of the error in the script source, if gluon reported one.
+    pub span: Option<Range<usize>>,
+    /// The sandbox limit the script broke, if that stopped it.
+    pub violation: Option<SandboxViolation>,
+}
+
+impl FormatError {
+    fn new(script: &str, error: &gluon::Error) -> Self {
+        let span = match error {
+            gluon::Error::Parse(x) => x
+                .errors()
+                .iter()
+                .next()
+                .and_then(|y| y.span.to_range(x.source())),
+            gluon::Error::Typecheck(x) => x
+                .errors()
+                .iter()
+                .next()
+                .and_then(|y| y.span.to_range(x.source())),
+            gluon::Error::Macro(x) => x
+                .errors()
+                .iter()
+                .next()
+                .and_then(|y| y.span.to_range(x.source())),
+            _ => None,
+        };
+        Self {
+            script: script.to_string(),
+            diagnostic: error.to_string(),
+            span,
+            violation: None,
+        }
+    }
+
+    fn sandbox(script: &str, violation: SandboxViolation) -> Self {
+        Self {
+            script: script.to_string(),
+            diagnostic: violation.to_string(),
+            span: None,
+            violation: Some(violation),
+        }
+    }
+
+    /// Converts the error of a sandboxed run, preferring the limit that stopped it.
+    fn from_run(script: &str, error: gluon::Error) -> Self {
+        let violation = SCRIPT_BUDGET
+            .with(|x| x.take_violation())
+            .or_else(|| match error {
+                gluon::Error::VM(gluon::vm::Error::OutOfMemory { .. }) => {
+                    Some(SandboxViolation::MemoryLimit(script_limits().max_memory))
+                }
+                gluon::Error::VM(gluon::vm::Error::StackOverflow(limit)) => {
+                    Some(SandboxViolation::StackLimit(limit))
+                }
+                _ => None,
+            });
+        match violation {
+            Some(x) => Self::sandbox(script, x),
+            None => Self::new(script, &error),
+        }
+    }
+}
+
+type FullNameFunction = fn(String, String, Vec<String>) -> String;
+type FlavorDescriptionLineFunction = fn(FlavorStatblock) -> String;
+type PositionalFlavorDescriptionLineFunction =
+    fn(String, u64, crate::AgeRange, String, String, String, String) -> String;
+type LineageLineFunction = fn(FlavorStatblock, String) -> String;
+type PositionalLineageLineFunction = fn(String) -> String;
+type FlavorLineFunction = fn(FlavorStatblock, String) -> String;
+
+/// Compiled formatters of one signature, keyed by their script text.
+/// Failures are cached too, so a broken script is only compiled once.
+type FunctionCache<F> = RefCell<HashMap<Arc<str>, Result<OwnedFunction<F>, FormatError>>>;
+
+const CORE_MODULE: &str = "npc_generator.core";
+
 thread_local! {
-    static GLUON_VM: RefCell<gluon::RootedThread> = {
-        let vm = gluon::new_vm();
+    static SCRIPT_BUDGET: Arc<Budget> = Default::default();
 
-        let source = gluon::vm::api::typ::make_source::<AgeRange>(&vm).unwrap();
+    static GLUON_VM: Result<gluon::RootedThread, FormatError> = {
+        let vm = gluon::new_vm();
 
-        vm.load_script("npc_generator.core", &source).unwrap();
-        RefCell::new(vm)
+        core_module_source(&vm)
+            .map_err(|x| FormatError::new(CORE_MODULE, &x.into()))
+            .and_then(|source| {
+                vm.load_script(CORE_MODULE, &source)
+                    .map_err(|x| FormatError::new(CORE_MODULE, &x))
+            })
+            .and_then(|()| add_random_module(&vm).map_err(|x| FormatError::new(RANDOM_MODULE, &x)))
+            .map(|_| {
+                // Every call counts as a step of the sandbox budget
+                let budget = SCRIPT_BUDGET.with(Arc::clone);
+                let mut context = vm.context();
+                context.set_hook(Some(Box::new(move |_, _| {
+                    Poll::Ready(
+                        budget
+                            .step()
+                            .map_err(|x| gluon::vm::Error::Message(x.to_string())),
+                    )
+                })));
+                context.set_hook_mask(HookFlags::CALL_FLAG);
+                drop(context);
+                vm
+            })
     };
+
+    static FULL_NAME_FUNCTIONS: FunctionCache<FullNameFunction> = Default::default();
+    static FLAVOR_DESCRIPTION_LINE_FUNCTIONS: FunctionCache<FlavorDescriptionLineFunction> =
+        Default::default();
+    static POSITIONAL_FLAVOR_DESCRIPTION_LINE_FUNCTIONS: FunctionCache<
+        PositionalFlavorDescriptionLineFunction,
+    > = Default::default();
+    static LINEAGE_LINE_FUNCTIONS: FunctionCache<LineageLineFunction> = Default::default();
+    static POSITIONAL_LINEAGE_LINE_FUNCTIONS: FunctionCache<PositionalLineageLineFunction> =
+        Default::default();
+    static FLAVOR_LINE_FUNCTIONS: FunctionCache<FlavorLineFunction> = Default::default();
 }
 
-fn create_format_vm() -> &'static LocalKey<RefCell<gluon::RootedThread>> {
+/// The records of `npc_generator.core`, with the fields in the order of the
+/// Rust structs they are pushed from.
+const CORE_RECORD_TYPES: &str = r#"
+type Attributes = {
+    strength : Int,
+    dexterity : Int,
+    constitution : Int,
+    intelligence : Int,
+    wisdom : Int,
+    charisma : Int
+}
+type SkillModifier = { skill : String, modifier : Int }
+type FlavorStatblock = {
+    name : String,
+    level : Int,
+    age : Int,
+    age_range : AgeRange,
+    sex : String,
+    ancestry : String,
+    ancestry_traits : Array String,
+    heritage : String,
+    heritage_traits : Array String,
+    background : String,
+    class : String,
+    size : String,
+    traits : Array String,
+    attributes : Attributes,
+    skills : Array SkillModifier,
+    perception : Int,
+    armor_class : Int,
+    fortitude_save : Int,
+    reflex_save : Int,
+    will_save : Int,
+    hit_points : Int,
+    land_speed : Int
+}
+"#;
+
+/// The `npc_generator.core` module, defining the types scripts get from Rust.
+fn core_module_source(vm: &Thread) -> gluon::vm::Result<String> {
+    let (age_range, age_range_type) = from_rust::<AgeRange>(vm)?;
+    Ok(format!(
+        "type {age_range} = {age_range_type}\n{CORE_RECORD_TYPES}\n\
+         {{ {age_range}, Attributes, SkillModifier, FlavorStatblock }}\n"
+    ))
+}
+
+fn create_format_vm() -> &'static LocalKey<Result<gluon::RootedThread, FormatError>> {
     &GLUON_VM
 }
 
-impl Formats {
-    pub async fn format_full_name<'a>(
-        &self,
-        first_name: &'a str,
-        surname: &'a str,
-        additional_names: Vec<&'a str>,
-    ) -> String {
-        let mut function = create_format_vm().with(|vm| {
-        let (function, _) = vm.borrow()
-            .run_expr::<gluon::vm::api::OwnedFunction<fn(&'a str, &'a str, Vec<&'a str>) -> String>>(
-                "formatter",
-                &self.full_name.0
-            )
-            .unwrap();
+/// Resets the budget and limits of this thread's VM for a new run.
+fn start_sandboxed_run(vm: &gluon::RootedThread, limits: ScriptLimits) {
+    SCRIPT_BUDGET.with(|x| x.start(limits));
+    vm.context().set_max_stack_size(limits.max_stack_size);
+    vm.set_memory_limit(vm.allocated_memory().saturating_add(limits.max_memory));
+}
 
-        function
-        });
+/// Calls a compiled script within the limits of the sandbox, with
+/// `npc_generator.random` seeded from `rng`.
+async fn run_sandboxed<T, C>(
+    script: &str,
+    rng: &mut impl Rng,
+    call: impl FnOnce() -> C,
+) -> Result<T, FormatError>
+where
+    C: Future<Output = Result<T, gluon::vm::Error>>,
+{
+    create_format_vm().with(|vm| {
+        vm.as_ref()
+            .map(|vm| start_sandboxed_run(vm, script_limits()))
+            .map_err(Clone::clone)
+    })?;
+    seed_script_rng(rng.gen());
+    call()
+        .await
+        .map_err(|x| FormatError::from_run(script, x.into()))
+}
 
+/// Returns the function compiled from `script` on this thread's VM.
+///
+/// Compiling and typechecking is far more expensive than running a
+/// formatter, so every distinct script is only compiled once per VM.
+fn compiled_function<F>(
+    cache: &'static LocalKey<FunctionCache<F>>,
+    name: &str,
+    script: &str,
+) -> Result<OwnedFunction<F>, FormatError>
+where
+    F: Clone + 'static,
+    OwnedFunction<F>: for<'vm, 'value> Getable<'vm, 'value> + VmType + Send,
+{
+    cache.with(|cache| {
+        if let Some(function) = cache.borrow().get(script) {
+            return function.clone();
+        }
+
+        let function = check_imports(script)
+            .map_err(|x| FormatError::sandbox(name, x))
+            .and_then(|()| {
+                create_format_vm().with(|vm| {
+                    let vm = vm.as_ref().map_err(Clone::clone)?;
+                    // Compiling loads the imported modules, which is slow the first
+                    // time, so only the step and stack limits apply
+                    start_sandboxed_run(
+                        vm,
+                        ScriptLimits {
+                            max_memory: usize::MAX,
+                            timeout: Duration::MAX,
+                            ..script_limits()
+                        },
+                    );
+                    vm.run_expr::<OwnedFunction<F>>(name, script)
+                        .map(|(function, _)| function)
+                        .map_err(|x| FormatError::from_run(name, x))
+                })
+            });
+        cache.borrow_mut().insert(script.into(), function.clone());
         function
-            .call_async(first_name, surname, additional_names)
-            .await
-            .unwrap()
+    })
+}
+
+/// A script compiled for the signature taking a [`FlavorStatblock`], or for
+/// the positional signature of packs written before it.
+enum Adapted<R, P> {
+    Record(OwnedFunction<R>),
+    Positional(OwnedFunction<P>),
+}
+
+/// Compiles `script` for the record signature, falling back to the
+/// positional one. Errors are those of the record signature.
+fn compiled_adapted<R, P>(
+    record_cache: &'static LocalKey<FunctionCache<R>>,
+    positional_cache: &'static LocalKey<FunctionCache<P>>,
+    name: &str,
+    script: &str,
+) -> Result<Adapted<R, P>, FormatError>
+where
+    R: Clone + 'static,
+    OwnedFunction<R>: for<'vm, 'value> Getable<'vm, 'value> + VmType + Send,
+    P: Clone + 'static,
+    OwnedFunction<P>: for<'vm, 'value> Getable<'vm, 'value> + VmType + Send,
+{
+    match compiled_function(record_cache, name, script) {
+        Ok(function) => Ok(Adapted::Record(function)),
+        Err(err) => compiled_function(positional_cache, name, script)
+            .map(Adapted::Positional)
+            .map_err(|_| err),
+    }
+}
+
+impl Formats {
+    /// Compiles the scripts and checks them against their expected signatures.
+    pub fn check(&self) -> Result<(), FormatError> {
+        compiled_function(&FULL_NAME_FUNCTIONS, "full_name", &self.full_name.0)?;
+        for (name, script) in &self.lines {
+            if FlavorLine::from_name(name).is_some() {
+                Self::check_flavor_line(name, script.as_ref())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles a [`FlavorLine`] script of the data pack or of an ancestry.
+    pub fn check_flavor_line(name: &str, script: &str) -> Result<(), FormatError> {
+        compiled_function(&FLAVOR_LINE_FUNCTIONS, name, script).map(|_| ())
+    }
+
+    /// Compiles the flavor description line script shared by all ancestries.
+    pub fn check_flavor_description_line(script: &str) -> Result<(), FormatError> {
+        compiled_adapted(
+            &FLAVOR_DESCRIPTION_LINE_FUNCTIONS,
+            &POSITIONAL_FLAVOR_DESCRIPTION_LINE_FUNCTIONS,
+            "default_format_flavor_description_line",
+            script,
+        )
+        .map(|_| ())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub async fn format_flavor_description_line<'a>(
+    pub async fn format_full_name(
         &self,
-        default: &str,
-        name: &'a str,
-        age: u64,
-        age_range: crate::AgeRange,
-        sex: &'a str,
-        ancestry_name: &'a str,
-        heritage_name: &'a str,
-        job_name: &'a str,
-    ) -> String {
-        let mut function = create_format_vm().with(|vm| {
-            let (function, _) = vm
-                .borrow()
-                .run_expr::<gluon::vm::api::OwnedFunction<
-                    fn(&'a str, u64, crate::AgeRange, &'a str, &'a str, &'a str, &'a str) -> String,
-                >>("formatter", default)
-                .unwrap();
-            function
-        });
-        function
-            .call_async(
-                name,
-                age,
-                age_range,
-                sex,
-                ancestry_name,
-                heritage_name,
-                job_name,
+        rng: &mut impl Rng,
+        first_name: &str,
+        surname: &str,
+        additional_names: Vec<&str>,
+    ) -> Result<String, FormatError> {
+        let mut function = compiled_function(&FULL_NAME_FUNCTIONS, "full_name", &self.full_name.0)?;
+
+        run_sandboxed("full_name", rng, || {
+            function.call_async(
+                first_name.to_string(),
+                surname.to_string(),
+                additional_names.into_iter().map(String::from).collect(),
             )
-            .await
-            .unwrap()
+        })
+        .await
+    }
+
+    /// Runs the flavor description line `script` of the data pack.
+    ///
+    /// The script gets the [`FlavorStatblock`], or for older packs the name,
+    /// age, age range, sex, ancestry, heritage and background as arguments.
+    pub async fn format_flavor_description_line(
+        &self,
+        rng: &mut impl Rng,
+        script: &str,
+        statblock: &FlavorStatblock,
+    ) -> Result<String, FormatError> {
+        const NAME: &str = "default_format_flavor_description_line";
+        match compiled_adapted(
+            &FLAVOR_DESCRIPTION_LINE_FUNCTIONS,
+            &POSITIONAL_FLAVOR_DESCRIPTION_LINE_FUNCTIONS,
+            NAME,
+            script,
+        )? {
+            Adapted::Record(mut function) => {
+                run_sandboxed(NAME, rng, || function.call_async(statblock.clone())).await
+            }
+            Adapted::Positional(mut function) => {
+                let (name, age, age_range, sex, ancestry, heritage, job) =
+                    statblock.description_line_arguments();
+                run_sandboxed(NAME, rng, || {
+                    function.call_async(name, age, age_range, sex, ancestry, heritage, job)
+                })
+                .await
+            }
+        }
+    }
+
+    /// Runs the script of the line on `default`, the line generated in Rust.
+    ///
+    /// The ancestry's script in `lines` wins over the one in `scripts`, the
+    /// scripts of the data pack. Without either, `default` is returned as it is.
+    pub async fn format_flavor_line(
+        &self,
+        rng: &mut impl Rng,
+        scripts: &GeneratorScripts,
+        statblock: &FlavorStatblock,
+        line: FlavorLine,
+        default: String,
+    ) -> Result<String, FormatError> {
+        let Some(script) = self
+            .lines
+            .get(line.name())
+            .map(AsRef::as_ref)
+            .or_else(|| scripts.get(line.name()))
+        else {
+            return Ok(default);
+        };
+        let mut function = compiled_function(&FLAVOR_LINE_FUNCTIONS, line.name(), script)?;
+        run_sandboxed(line.name(), rng, || {
+            function.call_async(statblock.clone(), default)
+        })
+        .await
     }
 }
 
 impl HeritageFormats {
-    pub async fn format_lineage_line<'a>(&self, lineage: &'a str) -> String {
-        let mut function = create_format_vm().with(|vm| {
-            let (function, _) = vm
-                .borrow()
-                .run_expr::<gluon::vm::api::OwnedFunction<fn(&'a str) -> String>>(
-                    "formatter",
-                    &self.lineage_line.0,
-                )
-                .unwrap();
-
-            function
-        });
-        function.call_async(lineage).await.unwrap()
+    /// Compiles the scripts and checks them against their expected signatures.
+    pub fn check(&self) -> Result<(), FormatError> {
+        compiled_adapted(
+            &LINEAGE_LINE_FUNCTIONS,
+            &POSITIONAL_LINEAGE_LINE_FUNCTIONS,
+            "lineage_line",
+            &self.lineage_line.0,
+        )
+        .map(|_| ())
+    }
+
+    /// Runs the lineage line script, which gets the [`FlavorStatblock`] and
+    /// the lineage, or for older packs only the lineage.
+    pub async fn format_lineage_line(
+        &self,
+        rng: &mut impl Rng,
+        statblock: &FlavorStatblock,
+        lineage: &str,
+    ) -> Result<String, FormatError> {
+        match compiled_adapted(
+            &LINEAGE_LINE_FUNCTIONS,
+            &POSITIONAL_LINEAGE_LINE_FUNCTIONS,
+            "lineage_line",
+            &self.lineage_line.0,
+        )? {
+            Adapted::Record(mut function) => {
+                run_sandboxed("lineage_line", rng, || {
+                    function.call_async(statblock.clone(), lineage.to_string())
+                })
+                .await
+            }
+            Adapted::Positional(mut function) => {
+                run_sandboxed("lineage_line", rng, || {
+                    function.call_async(lineage.to_string())
+                })
+                .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_description_line_arguments() {
+        let statblock = FlavorStatblock {
+            name: "Amiri".into(),
+            age: 27,
+            sex: "female".into(),
+            ancestry: "Human".into(),
+            background: "Barkeep".into(),
+            class: "Barbarian".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            (
+                "Amiri".to_string(),
+                27,
+                AgeRange::Adult,
+                " female".to_string(),
+                "Human".to_string(),
+                String::new(),
+                "Barkeep".to_string()
+            ),
+            statblock.description_line_arguments()
+        );
     }
 }
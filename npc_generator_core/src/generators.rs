@@ -1,4 +1,4 @@
-use self::formats::Formats;
+use self::formats::{FlavorLine, FlavorStatblock, FormatError, Formats};
 use self::weight_presets::WeightPreset;
 
 use super::*;
@@ -7,7 +7,7 @@ use rand::distributions::Distribution;
 use rand::seq::{IteratorRandom, SliceRandom};
 use rand::{rngs, Rng, SeedableRng};
 use std::borrow::Cow;
-use std::collections::{HashMap, LinkedList};
+use std::collections::{BTreeMap, HashMap, LinkedList};
 use std::sync::Arc;
 use thiserror::Error;
 use tokio;
@@ -16,20 +16,109 @@ use tokio;
 pub struct GeneratorData {
     pub ancestries: WeightMap<Ancestry>,
     pub normal_heritage_weight: f64,
-    pub versitile_heritages: WeightMap<Heritage>,
+    pub versatile_heritages: WeightMap<Heritage>,
     pub heritages: HashMap<Trait, WeightMap<String>>,
     pub backgrounds: WeightMap<Background>,
     pub names: HashMap<Trait, HashMap<String, WeightMap<String>>>,
     pub archetypes: Vec<Archetype>,
+    #[serde(default)]
+    pub sentences: SentenceTemplates,
+    #[serde(default)]
+    pub personality: Personality,
 }
 
+impl GeneratorData {
+    /// Typechecks every gluon script of the data pack, so broken packs are
+    /// rejected when they are loaded instead of during generation.
+    pub fn validate_scripts(
+        &self,
+        scripts: &GeneratorScripts,
+    ) -> Result<(), ScriptValidationError> {
+        let mut failures = Vec::new();
+        if let Err(err) = Formats::check_flavor_description_line(scripts.flavor_description_line())
+        {
+            failures.push((String::from("scripts"), err));
+        }
+        for line in FlavorLine::values() {
+            if let Some(Err(err)) = scripts
+                .get(line.name())
+                .map(|x| Formats::check_flavor_line(line.name(), x))
+            {
+                failures.push((String::from("scripts"), err));
+            }
+        }
+        for ancestry in self.ancestries.keys() {
+            if let Err(err) = ancestry.formats().check() {
+                failures.push((format!("ancestry {}", ancestry.name()), err));
+            }
+        }
+        for heritage in self.versatile_heritages.keys() {
+            if let Err(err) = heritage.formats().check() {
+                failures.push((format!("heritage {}", heritage.name()), err));
+            }
+        }
+        failures.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ScriptValidationError { failures })
+        }
+    }
+}
+
+/// The sexes `generate_sex` chooses from for ancestries that aren't asexual.
+pub const GENERATED_SEXES: [&str; 2] = ["male", "female"]; // TODO add diversity
+
 pub struct Generator<R: rand::Rng + Send + Sync> {
     random_number_generator: R,
     pub data: Arc<GeneratorData>,
     pub scripts: Arc<GeneratorScripts>,
 }
+
+/// Name of the script formatting the first flavor line, the only one a data
+/// pack has to contain.
+pub const FLAVOR_DESCRIPTION_LINE_SCRIPT: &str = "default_format_flavor_description_line";
+
+/// The gluon scripts of a data pack by name, each read from `scripts/<name>.glu`.
+#[derive(Debug, Default, Clone)]
 pub struct GeneratorScripts {
-    pub default_format_flavor_description_line_script: String,
+    scripts: BTreeMap<String, String>,
+}
+
+impl GeneratorScripts {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.scripts.get(name).map(String::as_str)
+    }
+
+    /// Adds the script, replacing one of the same name.
+    pub fn insert(&mut self, name: impl Into<String>, script: impl Into<String>) {
+        self.scripts.insert(name.into(), script.into());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.scripts.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// The [`FLAVOR_DESCRIPTION_LINE_SCRIPT`], empty if the pack has none.
+    pub fn flavor_description_line(&self) -> &str {
+        self.get(FLAVOR_DESCRIPTION_LINE_SCRIPT).unwrap_or_default()
+    }
+}
+
+impl Extend<(String, String)> for GeneratorScripts {
+    fn extend<T: IntoIterator<Item = (String, String)>>(&mut self, iter: T) {
+        self.scripts.extend(iter)
+    }
+}
+
+impl IntoIterator for GeneratorScripts {
+    type Item = (String, String);
+    type IntoIter = std::collections::btree_map::IntoIter<String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.scripts.into_iter()
+    }
 }
 
 #[derive(Error, Debug)]
@@ -56,6 +145,25 @@ pub enum GenerationError {
     BackgroundGenerationError(#[from] BackgroundGenerationError),
     #[error(transparent)]
     SexGenerationError(#[from] SexGenerationError),
+    #[error(transparent)]
+    FormatError(#[from] FormatError),
+}
+
+/// Every data pack script that failed to compile, with the name of the
+/// ancestry or heritage that owns it.
+#[derive(Error, Debug)]
+pub struct ScriptValidationError {
+    pub failures: Vec<(String, FormatError)>,
+}
+
+impl std::fmt::Display for ScriptValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} script(s) failed to compile", self.failures.len())?;
+        for (owner, error) in &self.failures {
+            write!(f, "\n{owner}: {error}")?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Error, Debug)]
@@ -108,6 +216,8 @@ pub enum FlavorGenerationError {
     HeritageIsNone,
     #[error(transparent)]
     FlavorLineGenerationError(#[from] FlavorLineGenerationError),
+    #[error(transparent)]
+    FormatError(#[from] FormatError),
 }
 
 #[derive(Error, Debug)]
@@ -249,7 +359,7 @@ where {
                             &self.data.names,
                             &sex,
                         )
-                        .await
+                        .await?
                     } else {
                         String::default()
                     }
@@ -303,6 +413,8 @@ where {
             let mut statblock = statblock.clone();
             statblock.set_ancestry(Some(ancestry.clone()));
             statblock.set_heritage(heritage.clone());
+            // Set before the flavor, so scripts and templates see the class
+            statblock.set_class(background.name());
             statblock.set_flavor(if options.enable_flavor_text {
                 let mut flavor_rng = rngs::StdRng::from_rng(&mut rng).unwrap();
                 self.generate_flavor(
@@ -311,12 +423,12 @@ where {
                     &mut flavor_rng,
                     &statblock,
                     &background,
+                    options.units,
                 )
                 .await?
             } else {
                 Default::default()
             });
-            statblock.set_class(background.name());
 
             debug!("Generated statblock {statblock:?}");
             statblock
@@ -337,7 +449,7 @@ where {
             let heritage = {
                 let (values, distribution) = &self
                     .data
-                    .versitile_heritages
+                    .versatile_heritages
                     .split_weights()
                     .map_err(|_| HeritageGenerationError)?;
                 values[distribution.sample(rng)].clone()
@@ -373,41 +485,67 @@ where {
         rng: &mut impl Rng,
         unflavored_statblock: &Statblock,
         background: &Background,
+        units: Units,
     ) -> Result<NpcFlavor, FlavorGenerationError> {
         let ancestry = unflavored_statblock
             .ancestry()
             .ok_or(FlavorGenerationError::AncestryIsNone)?;
         let heritage = unflavored_statblock.heritage();
+        let statblock = FlavorStatblock::new(unflavored_statblock, &background.name());
+        let mut slots = SentenceSlots::new(&statblock);
+
+        let hair_and_eyes_line =
+            generate_flavor_hair_and_eyes_line(rng, formats, ancestry, heritage, &mut slots)?;
+        let skin_line = generate_flavor_skin_line(rng, formats, ancestry, heritage, &mut slots);
+        let size_and_build_line = generate_size_and_build(
+            rng,
+            formats,
+            ancestry,
+            unflavored_statblock.age_range(),
+            units,
+            &mut slots,
+        );
+        let face_line = generate_flavor_face_line(
+            rng,
+            formats,
+            ancestry,
+            heritage,
+            unflavored_statblock,
+            &mut slots,
+        );
+        let habit_line = generate_flavor_habit_line(
+            rng,
+            &self.data.personality,
+            ancestry,
+            heritage,
+            unflavored_statblock,
+            &background.name(),
+            &mut slots,
+        );
+
+        // Drawn after the lines above, so templates and scripts don't change how they come out
+        let mut rng = rngs::StdRng::from_rng(rng).unwrap();
+        let sources = FlavorLineSources {
+            formats,
+            scripts: &generator_scripts,
+            sentences: &self.data.sentences,
+            statblock: &statblock,
+            slots,
+        };
         Ok(NpcFlavor {
-            description_line: generate_flavor_description_line(
-                generator_scripts,
-                formats,
-                unflavored_statblock.name(),
-                unflavored_statblock.age(),
-                unflavored_statblock.age_range(),
-                unflavored_statblock.sex(),
-                ancestry.name(),
-                heritage.map(Heritage::name).as_deref(),
-                background.name(),
-                None,
-            )
-            .await,
-            lineage_line: generate_lineage_line(heritage, formats).await,
-            hair_and_eyes_line: generate_flavor_hair_and_eyes_line(
-                rng, formats, ancestry, heritage,
-            )?,
-            skin_line: generate_flavor_skin_line(rng, formats, ancestry, heritage),
-
-            size_and_build_line: generate_size_and_build(
-                rng,
-                formats,
-                ancestry,
-                unflavored_statblock.age(),
-                unflavored_statblock.age_range(),
-                heritage,
-            ),
-            face_line: generate_flavor_face_line(rng, formats, ancestry, unflavored_statblock),
-            habit_line: generate_flavor_habit_line(rng, formats, ancestry),
+            hair_and_eyes_line: sources
+                .line(&mut rng, FlavorLine::HairAndEyes, hair_and_eyes_line)
+                .await?,
+            skin_line: sources.line(&mut rng, FlavorLine::Skin, skin_line).await?,
+            size_and_build_line: sources
+                .line(&mut rng, FlavorLine::SizeAndBuild, size_and_build_line)
+                .await?,
+            face_line: sources.line(&mut rng, FlavorLine::Face, face_line).await?,
+            habit_line: sources
+                .line(&mut rng, FlavorLine::Habit, habit_line)
+                .await?,
+            description_line: sources.description_line(&mut rng).await?,
+            lineage_line: sources.lineage_line(&mut rng, heritage).await?,
         })
     }
 
@@ -416,8 +554,7 @@ where {
         random_number_generator: &mut impl Rng,
         _ancestry: &Ancestry,
     ) -> Result<String, GenerationError> {
-        let sexes = ["male", "female"]; // TODO add diversity
-        Ok(sexes
+        Ok(GENERATED_SEXES
             .choose(random_number_generator)
             .ok_or(SexGenerationError)?
             .to_string())
@@ -430,7 +567,7 @@ where {
         ancestry: &Ancestry,
         names: &HashMap<Trait, HashMap<String, WeightMap<String>>>,
         sex: &str,
-    ) -> String {
+    ) -> Result<String, FormatError> {
         let traits: Vec<Trait> = {
             let filtered_traits: HashSet<_> = HashSet::from_iter(traits);
             let available_name_traits: HashSet<_> = HashSet::from_iter(names.keys());
@@ -452,7 +589,7 @@ where {
                 "No names for given sex `{sex}` present on name trait `{}`",
                 name_trait
             );
-            return String::from("@@NAME_ERROR@@");
+            return Ok(String::from("@@NAME_ERROR@@"));
         };
         let first_name = {
             let (names, weights) = names.split_weights().unwrap();
@@ -463,53 +600,234 @@ where {
             let (surnames, weights) = surnames.split_weights().unwrap();
             surnames[name_rng.sample(weights)]
         } else {
-            return first_name;
+            return Ok(first_name);
         };
 
         ancestry
             .formats()
-            .format_full_name(&first_name, surname.as_ref(), vec![])
+            .format_full_name(name_rng, &first_name, surname.as_ref(), vec![])
             .await
     }
 }
 
+/// What the flavor lines of an NPC are made from, once the parts of the
+/// lines are rolled.
+struct FlavorLineSources<'a> {
+    formats: &'a Formats,
+    scripts: &'a GeneratorScripts,
+    sentences: &'a SentenceTemplates,
+    statblock: &'a FlavorStatblock,
+    slots: SentenceSlots,
+}
+
+impl FlavorLineSources<'_> {
+    /// The line from a sentence template of the data pack, or else from its scripts.
+    async fn line(
+        &self,
+        rng: &mut impl Rng,
+        line: FlavorLine,
+        default: String,
+    ) -> Result<String, FormatError> {
+        let slots = self.slots.with_line(default.as_str());
+        match self
+            .sentences
+            .generate(rng, line.name(), self.statblock, &slots)
+        {
+            Some(x) => Ok(x),
+            None => {
+                self.formats
+                    .format_flavor_line(rng, self.scripts, self.statblock, line, default)
+                    .await
+            }
+        }
+    }
+
+    async fn description_line(&self, rng: &mut impl Rng) -> Result<String, FormatError> {
+        match self
+            .sentences
+            .generate(rng, DESCRIPTION_LINE, self.statblock, &self.slots)
+        {
+            Some(x) => Ok(x),
+            None => {
+                self.formats
+                    .format_flavor_description_line(
+                        rng,
+                        self.scripts.flavor_description_line(),
+                        self.statblock,
+                    )
+                    .await
+            }
+        }
+    }
+
+    async fn lineage_line(
+        &self,
+        rng: &mut impl Rng,
+        heritage: Option<&Heritage>,
+    ) -> Result<Option<String>, FormatError> {
+        let Some((heritage, lineage)) = heritage.and_then(|x| Some((x, x.lineage()?))) else {
+            return Ok(None);
+        };
+        let mut slots = self.slots.clone();
+        slots.insert("lineage", lineage);
+        match self
+            .sentences
+            .generate(rng, LINEAGE_LINE, self.statblock, &slots)
+        {
+            Some(x) => Ok(Some(x)),
+            None => heritage
+                .formats()
+                .format_lineage_line(rng, self.statblock, lineage)
+                .await
+                .map(Some),
+        }
+    }
+}
+
 fn generate_size_and_build(
-    _rng: &mut impl Rng,
+    rng: &mut impl Rng,
     _formats: &Formats,
     ancestry: &Ancestry,
-    _age: u64,
-    _age_range: AgeRange,
-    _heritage: Option<&Heritage>,
+    age_range: AgeRange,
+    units: Units,
+    slots: &mut SentenceSlots,
 ) -> String {
+    if let Some(body) = ancestry.body() {
+        let (height, weight) = body.roll(rng, age_range);
+        let height = units.format_height(height);
+        let weight = units.format_weight(weight);
+        let line = format!("They are {height} tall and weigh {weight}");
+        slots.insert("height", height);
+        slots.insert("weight", weight);
+        return match body.builds.split_weights() {
+            Ok((builds, distribution)) => {
+                let build: &str = builds[distribution.sample(rng)];
+                slots.insert("build", build);
+                format!("{line}, with {} {build} build.", indefinite_article(build))
+            }
+            Err(_) => format!("{line}."),
+        };
+    }
+
     let bulk = match ancestry.size() {
         Size::Tiny => 1,
         Size::Small => 3,
         Size::Medium => 6,
         Size::Large => 12,
         Size::Huge => 24,
-        Size::Garganutan => 48,
+        Size::Gargantuan => 48,
     };
 
     format!("They have a bulk of {bulk}.")
 }
 
 fn generate_flavor_face_line(
-    _rng: &mut impl Rng,
+    rng: &mut impl Rng,
     _formats: &Formats,
-    _ancestry: &Ancestry,
-    _statblock: &Statblock,
+    ancestry: &Ancestry,
+    heritage: Option<&Heritage>,
+    statblock: &Statblock,
+    slots: &mut SentenceSlots,
 ) -> String {
-    // TODO
+    let face = match heritage {
+        Some(heritage) => Cow::Owned(ancestry.face().with(heritage.face())),
+        None => Cow::Borrowed(ancestry.face()),
+    };
 
-    "They have a face.".to_string()
+    let mut parts = Vec::new();
+    for (slot, table, noun) in [
+        ("face_shape", &face.shapes, "face"),
+        ("nose", &face.noses, "nose"),
+        ("ears", &face.ears, "ears"),
+        ("jaw", &face.jaws, "jaw"),
+    ] {
+        let Ok((values, distribution)) = table.split_weights() else {
+            continue;
+        };
+        let value: &str = values[distribution.sample(rng)];
+        slots.insert(slot, value);
+        parts.push(if noun == "ears" {
+            format!("{value} {noun}")
+        } else {
+            format!("{} {value} {noun}", indefinite_article(value))
+        });
+    }
+
+    let mut extras = Vec::new();
+    let facial_hair = face.facial_hair_for(statblock.sex(), statblock.age_range());
+    if let Ok(distribution) =
+        rand::distributions::WeightedIndex::new(facial_hair.iter().map(|x| x.weight))
+    {
+        let facial_hair = &facial_hair[distribution.sample(rng)].text;
+        slots.insert("facial_hair", facial_hair.as_ref());
+        extras.push(facial_hair.to_string());
+    }
+    let features = face
+        .features
+        .iter()
+        .filter(|x| rng.gen_bool(x.chance.clamp(0.0, 1.0)))
+        .map(|x| x.text.to_string())
+        .collect::<Vec<_>>();
+    if !features.is_empty() {
+        slots.insert("face_features", join_list(&features));
+        extras.extend(features);
+    }
+
+    match (parts.is_empty(), extras.is_empty()) {
+        (true, true) => "They have an unremarkable face.".to_string(),
+        (false, true) => format!("They have {}.", join_list(&parts)),
+        (true, false) => format!("They have {}.", join_list(&extras)),
+        (false, false) => format!(
+            "They have {}. They have {}.",
+            join_list(&parts),
+            join_list(&extras)
+        ),
+    }
+}
+
+fn indefinite_article(word: &str) -> &'static str {
+    match word.chars().next() {
+        Some('a' | 'e' | 'i' | 'o' | 'u' | 'A' | 'E' | 'I' | 'O' | 'U') => "an",
+        _ => "a",
+    }
+}
+
+/// `a, b and c`
+fn join_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
 }
+
 fn generate_flavor_habit_line(
-    _rng: &mut impl Rng,
-    _formats: &Formats,
-    _ancestry: &Ancestry,
+    rng: &mut impl Rng,
+    personality: &Personality,
+    ancestry: &Ancestry,
+    heritage: Option<&Heritage>,
+    statblock: &Statblock,
+    background: &str,
+    slots: &mut SentenceSlots,
 ) -> String {
-    // TODO
-    "They have habits.".to_string()
+    let traits = ancestry
+        .traits()
+        .iter()
+        .chain(heritage.map(|x| x.traits()).unwrap_or_default())
+        .cloned()
+        .collect::<Vec<_>>();
+    let rolled = personality.roll(
+        rng,
+        &PersonalityFilter {
+            background,
+            age_range: statblock.age_range(),
+            traits: &traits,
+        },
+    );
+    for (kind, text) in &rolled {
+        slots.insert(kind.name(), *text);
+    }
+    personality_line(rng, &rolled)
 }
 
 fn generate_stats(
@@ -717,72 +1035,12 @@ fn generate_stats(
     })
 }
 
-async fn generate_lineage_line(heritage: Option<&Heritage>, _formats: &Formats) -> Option<String> {
-    if let Some(heritage) = heritage {
-        match heritage
-            .lineage()
-            .as_ref()
-            .map(|lineage| heritage.formats().format_lineage_line(lineage))
-        {
-            Some(x) => Some(x.await),
-            None => None,
-        }
-    } else {
-        None
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-async fn generate_flavor_description_line(
-    generator_scripts: Arc<GeneratorScripts>,
-    formats: &Formats,
-    name: impl AsRef<str>,
-    age: u64,
-    age_range: AgeRange,
-    sex: impl AsRef<str>,
-    ancestry_name: impl AsRef<str>,
-    heritage_name: Option<&str>,
-    background_name: impl AsRef<str>,
-    class_name: Option<&str>,
-) -> String {
-    let name = name.as_ref();
-    let sex = sex.as_ref();
-    let ancestry_name = ancestry_name.as_ref();
-    let background_name = background_name.as_ref();
-    let job_name = match class_name {
-        Some(x) => x,
-        None => background_name,
-    };
-
-    let heritage_name = heritage_name
-        .map(|x| " ".to_owned() + x)
-        .unwrap_or("".to_string());
-
-    let sex = if sex.is_empty() {
-        sex.to_string()
-    } else {
-        format!(" {sex}")
-    };
-
-    formats
-        .format_flavor_description_line(
-            &generator_scripts.default_format_flavor_description_line_script,
-            name,
-            age,
-            age_range,
-            &sex,
-            ancestry_name,
-            &heritage_name,
-            job_name,
-        )
-        .await
-}
-
 fn generate_flavor_hairs(
     rng: &mut impl Rng,
     _formats: &Formats,
     ancestry: &Ancestry,
     _heritage: Option<&Heritage>,
+    slots: &mut SentenceSlots,
 ) -> Result<String, HairGenerationError> {
     let ancestry_hair_type = ancestry.possible_hair_type();
     let ancestry_hair_colors = ancestry.possible_hair_colors();
@@ -813,7 +1071,12 @@ fn generate_flavor_hairs(
     };
 
     let hair = ancestry.hair_substance();
-    Ok(format!("{hair_length}, {hair_type}, {hair_color} {hair}"))
+    let result = format!("{hair_length}, {hair_type}, {hair_color} {hair}");
+    slots.insert("hair_length", hair_length);
+    slots.insert("hair_type", hair_type);
+    slots.insert("hair_color", hair_color);
+    slots.insert("hair_substance", hair);
+    Ok(result)
 }
 
 fn generate_flavor_eyes(
@@ -821,6 +1084,7 @@ fn generate_flavor_eyes(
     _formats: &Formats,
     ancestry: &Ancestry,
     heritage: Option<&Heritage>,
+    slots: &mut SentenceSlots,
 ) -> String {
     let mut available_eye_colors: WeightMap<Cow<str>> = WeightMap::new();
     if let Some(x) = ancestry.possible_eye_colors() {
@@ -857,6 +1121,7 @@ fn generate_flavor_eyes(
             (dist.sample(rng), heterochromia_color)
         };
 
+    slots.insert("eye_color", eye_color);
     if has_heterochromia {
         let mut eye_color: &str = eye_color;
         while heterochromia_color == eye_color {
@@ -881,18 +1146,21 @@ fn generate_flavor_hair_and_eyes_line(
     formats: &Formats,
     ancestry: &Ancestry,
     heritage: Option<&Heritage>,
+    slots: &mut SentenceSlots,
 ) -> Result<String, FlavorLineGenerationError> {
-    Ok(format!(
-        "They have {} and {}.",
-        generate_flavor_hairs(&mut rng, formats, ancestry, heritage)?,
-        generate_flavor_eyes(&mut rng, formats, ancestry, heritage)
-    ))
+    let hair = generate_flavor_hairs(&mut rng, formats, ancestry, heritage, slots)?;
+    let eyes = generate_flavor_eyes(&mut rng, formats, ancestry, heritage, slots);
+    let result = format!("They have {hair} and {eyes}.");
+    slots.insert("hair", hair);
+    slots.insert("eyes", eyes);
+    Ok(result)
 }
 fn generate_flavor_skin_line(
     rng: &mut impl Rng,
     _formats: &Formats,
     ancestry: &Ancestry,
     _heritage: Option<&Heritage>,
+    slots: &mut SentenceSlots,
 ) -> String {
     let skin_texture: &str = {
         let (skin_textures, distribution) =
@@ -907,5 +1175,40 @@ fn generate_flavor_skin_line(
     }
     .as_ref();
     let skin: &str = ancestry.skin_substance();
+    slots.insert("skin", format!("{skin_texture} {skin_tone} {skin}"));
+    slots.insert("skin_texture", skin_texture);
+    slots.insert("skin_tone", skin_tone);
+    slots.insert("skin_substance", skin);
     format!("They have {skin_texture} {skin_tone} {skin}.")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::{read_generator_data, DirectorySource};
+
+    #[test]
+    fn test_archetype_sets_class() {
+        let mut source = DirectorySource::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../data"));
+        let (data, scripts) = read_generator_data(&mut source).unwrap();
+        let archetype = data.archetypes[0].clone();
+        let mut generator = Generator::new(
+            rngs::StdRng::seed_from_u64(0),
+            Arc::new(data),
+            Arc::new(scripts),
+        )
+        .unwrap();
+        let options = NpcOptions {
+            archetype: Some(archetype.clone()),
+            enable_flavor_text: false,
+            ..Default::default()
+        };
+        let statblock = generator.generate(&options, None).unwrap();
+
+        assert_eq!(archetype.name(), statblock.class());
+        assert_eq!(
+            archetype.name(),
+            FlavorStatblock::new(&statblock, &archetype.name()).class
+        );
+    }
+}
@@ -6,7 +6,9 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-use crate::{formats::HeritageFormats, NamedElement, Trait, ValidAncestries, WeightMap};
+use crate::{
+    formats::HeritageFormats, FaceFeatures, NamedElement, Trait, ValidAncestries, WeightMap,
+};
 
 type HeritageString = Arc<str>;
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -21,6 +23,8 @@ pub struct Heritage {
     additional_hair_colors: WeightMap<HeritageString>,
     force_heterochromia: Option<HeritageString>,
     #[serde(default)]
+    face: FaceFeatures,
+    #[serde(default)]
     formats: HeritageFormats,
 }
 
@@ -35,6 +39,7 @@ impl Heritage {
         additional_hair_colors: impl Into<WeightMap<HeritageString>>,
         force_heterochromia: Option<impl AsRef<str>>,
         prd_reference: Option<impl AsRef<str>>,
+        face: FaceFeatures,
         formats: HeritageFormats,
     ) -> Self {
         Self {
@@ -46,6 +51,7 @@ impl Heritage {
             additional_hair_colors: additional_hair_colors.into(),
             prd_reference: prd_reference.map(|x| x.as_ref().into()),
             force_heterochromia: force_heterochromia.map(|x| x.as_ref().into()),
+            face,
             formats,
         }
     }
@@ -74,6 +80,11 @@ impl Heritage {
         self.force_heterochromia.as_deref()
     }
 
+    /// The facial features added to the ones of the ancestry.
+    pub fn face(&self) -> &FaceFeatures {
+        &self.face
+    }
+
     pub fn formats(&self) -> &HeritageFormats {
         &self.formats
     }
@@ -95,10 +106,10 @@ impl NamedElement for Heritage {
     fn traits(&self) -> &[Trait] {
         &self.traits
     }
-    fn name(&self) -> Cow<str> {
+    fn name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
-    fn formatted_name(&self) -> Cow<str> {
+    fn formatted_name(&self) -> Cow<'_, str> {
         if let Some(ref lineage) = self.lineage {
             Cow::Owned(format!("{} ({})", self.name, lineage))
         } else {
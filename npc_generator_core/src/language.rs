@@ -26,11 +26,11 @@ impl NamedElement for Language {
         &self.traits
     }
 
-    fn name(&self) -> Cow<str> {
+    fn name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
 
-    fn formatted_name(&self) -> Cow<str> {
+    fn formatted_name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
 }
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
 pub mod generators;
+pub mod loader;
 mod newtypes;
 
 mod heritage;
@@ -12,6 +13,10 @@ pub use ancestry::*;
 mod statblock;
 pub use newtypes::*;
 pub use statblock::*;
+mod obsidian_note;
+pub use obsidian_note::*;
+mod pf2e_stats_parser;
+pub use pf2e_stats_parser::*;
 mod ability;
 pub use ability::*;
 mod traits;
@@ -24,6 +29,10 @@ mod language;
 pub use language::*;
 mod size;
 pub use size::*;
+mod face;
+pub use face::*;
+mod body;
+pub use body::*;
 mod dice;
 pub use dice::*;
 mod age_range;
@@ -43,4 +52,16 @@ pub use archetype::*;
 mod npc_options;
 pub use npc_options::*;
 pub mod formats;
+mod lint;
+pub mod sandbox;
+mod script_random;
+pub use lint::*;
+mod overlay;
+pub use overlay::*;
+mod sentences;
+pub use sentences::*;
+mod personality;
+pub use personality::*;
+mod manifest;
+pub use manifest::*;
 pub mod weight_presets;
@@ -0,0 +1,635 @@
+use std::{collections::HashSet, fmt::Display, hash::Hash};
+
+use crate::{
+    formats::{FlavorLine, Formats},
+    generators::{GeneratorData, GeneratorScripts, GENERATED_SEXES},
+    sentence_lines, slot_name, AgeRanges, FaceFeatures, NamedElement, Personality, PersonalityKind,
+    SentenceTemplates, Trait, ValidAncestries, WeightMap, LINEAGE_LINE, SENTENCE_SLOTS,
+};
+
+/// An inconsistency in a data pack, found by [`GeneratorData::lint`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LintIssue {
+    /// The data pack file containing the entry, e.g. `names.ron`.
+    pub file: &'static str,
+    /// The entry inside the file, e.g. an ancestry name.
+    pub entry: String,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn new(file: &'static str, entry: impl AsRef<str>, message: impl AsRef<str>) -> Self {
+        Self {
+            file,
+            entry: entry.as_ref().to_string(),
+            message: message.as_ref().to_string(),
+        }
+    }
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.file, self.entry, self.message)
+    }
+}
+
+const ANCESTRIES_FILE: &str = "ancestries.ron";
+const HERITAGES_FILE: &str = "heritages.ron";
+const BACKGROUNDS_FILE: &str = "backgrounds.ron";
+const NAMES_FILE: &str = "names.ron";
+const SCRIPTS_FILE: &str = "scripts/default_format_flavor_description_line.glu";
+const SENTENCES_FILE: &str = "sentences.ron";
+const PERSONALITY_FILE: &str = "personality.ron";
+
+/// Pushes an issue if no value of the map can ever be chosen.
+fn lint_weights<K: Hash + Eq>(
+    issues: &mut Vec<LintIssue>,
+    file: &'static str,
+    entry: impl AsRef<str>,
+    what: &str,
+    weights: &WeightMap<K>,
+) {
+    if weights.is_empty() {
+        issues.push(LintIssue::new(file, entry, format!("{what} is empty")));
+    } else if weights.split_weights().is_err() {
+        issues.push(LintIssue::new(
+            file,
+            entry,
+            format!("{what} has no positive weight"),
+        ));
+    }
+}
+
+fn lint_age_ranges(issues: &mut Vec<LintIssue>, entry: &str, age_ranges: &AgeRanges) {
+    let bounds = [
+        ("child", age_ranges.child),
+        ("youth", age_ranges.youth),
+        ("adulthood", age_ranges.adulthood),
+        ("middle_age", age_ranges.middle_age),
+        ("old", age_ranges.old),
+        ("venerable", age_ranges.venerable),
+        ("lifespan", age_ranges.lifespan),
+    ];
+    for pair in bounds.windows(2) {
+        let ((lower_name, lower), (upper_name, upper)) = (pair[0], pair[1]);
+        if lower >= upper {
+            issues.push(LintIssue::new(
+                ANCESTRIES_FILE,
+                entry,
+                format!("age_ranges are not increasing: {lower_name} is {lower}, {upper_name} is {upper}"),
+            ));
+        }
+    }
+}
+
+fn lint_face(issues: &mut Vec<LintIssue>, file: &'static str, entry: &str, face: &FaceFeatures) {
+    for (what, table) in [
+        ("face.shapes", &face.shapes),
+        ("face.noses", &face.noses),
+        ("face.ears", &face.ears),
+        ("face.jaws", &face.jaws),
+    ] {
+        if !table.is_empty() {
+            lint_weights(issues, file, entry, what, table);
+        }
+    }
+    for facial_hair in &face.facial_hair {
+        for sex in facial_hair
+            .sexes
+            .iter()
+            .filter(|x| !GENERATED_SEXES.contains(&x.as_str()))
+        {
+            issues.push(LintIssue::new(
+                file,
+                entry,
+                format!(
+                    "facial hair `{}` names unknown sex `{sex}`",
+                    facial_hair.text
+                ),
+            ));
+        }
+    }
+    for feature in face
+        .features
+        .iter()
+        .filter(|x| !(0.0..=1.0).contains(&x.chance))
+    {
+        issues.push(LintIssue::new(
+            file,
+            entry,
+            format!(
+                "facial feature `{}` has chance {} outside of 0 to 1",
+                feature.text, feature.chance
+            ),
+        ));
+    }
+}
+
+fn lint_sentences(
+    issues: &mut Vec<LintIssue>,
+    sentences: &SentenceTemplates,
+    ancestry_names: &HashSet<String>,
+    heritage_names: &HashSet<String>,
+    background_names: &HashSet<String>,
+) {
+    for (line, templates) in &sentences.0 {
+        if !sentence_lines().any(|x| x == line) {
+            issues.push(LintIssue::new(SENTENCES_FILE, line, "unknown line"));
+        }
+        if templates.iter().all(|x| x.weight == 0) {
+            issues.push(LintIssue::new(
+                SENTENCES_FILE,
+                line,
+                "no template has a positive weight",
+            ));
+        }
+        for template in templates {
+            for slot in template.slots() {
+                let (name, _) = slot_name(slot);
+                if !SENTENCE_SLOTS.contains(&name.as_str())
+                    || (name == "lineage" && line != LINEAGE_LINE)
+                {
+                    issues.push(LintIssue::new(
+                        SENTENCES_FILE,
+                        line,
+                        format!("`{}` uses unknown slot `{slot}`", template.text),
+                    ));
+                }
+            }
+            for ancestry in template
+                .ancestries
+                .iter()
+                .filter(|x| !ancestry_names.contains(*x))
+            {
+                issues.push(LintIssue::new(
+                    SENTENCES_FILE,
+                    line,
+                    format!("`{}` names unknown ancestry `{ancestry}`", template.text),
+                ));
+            }
+            for heritage in template
+                .heritages
+                .iter()
+                .filter(|x| !heritage_names.contains(*x))
+            {
+                issues.push(LintIssue::new(
+                    SENTENCES_FILE,
+                    line,
+                    format!("`{}` names unknown heritage `{heritage}`", template.text),
+                ));
+            }
+            for background in template
+                .backgrounds
+                .iter()
+                .filter(|x| !background_names.contains(*x))
+            {
+                issues.push(LintIssue::new(
+                    SENTENCES_FILE,
+                    line,
+                    format!(
+                        "`{}` names unknown background `{background}`",
+                        template.text
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn lint_personality(
+    issues: &mut Vec<LintIssue>,
+    personality: &Personality,
+    background_names: &HashSet<String>,
+    known_traits: &HashSet<Trait>,
+) {
+    for kind in PersonalityKind::values() {
+        let table = personality.table(*kind);
+        if !table.is_empty() && table.iter().all(|x| x.weight == 0) {
+            issues.push(LintIssue::new(
+                PERSONALITY_FILE,
+                kind.name(),
+                "no entry has a positive weight",
+            ));
+        }
+        for entry in table {
+            for background in entry
+                .backgrounds
+                .iter()
+                .filter(|x| !background_names.contains(*x))
+            {
+                issues.push(LintIssue::new(
+                    PERSONALITY_FILE,
+                    kind.name(),
+                    format!("`{}` names unknown background `{background}`", entry.text),
+                ));
+            }
+            for unknown_trait in entry.traits.iter().filter(|x| !known_traits.contains(*x)) {
+                issues.push(LintIssue::new(
+                    PERSONALITY_FILE,
+                    kind.name(),
+                    format!(
+                        "`{}` names trait `{unknown_trait}` no ancestry or heritage has",
+                        entry.text
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+impl GeneratorData {
+    /// Checks the data for inconsistencies that would only show up as odd
+    /// results or failures during generation.
+    pub fn lint(&self, scripts: &GeneratorScripts) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+
+        lint_weights(
+            &mut issues,
+            ANCESTRIES_FILE,
+            "*",
+            "ancestry list",
+            &self.ancestries,
+        );
+        lint_weights(
+            &mut issues,
+            HERITAGES_FILE,
+            "*",
+            "heritage list",
+            &self.versatile_heritages,
+        );
+        lint_weights(
+            &mut issues,
+            BACKGROUNDS_FILE,
+            "*",
+            "background list",
+            &self.backgrounds,
+        );
+
+        if let Err(err) = Formats::check_flavor_description_line(scripts.flavor_description_line())
+        {
+            issues.push(LintIssue::new(SCRIPTS_FILE, "*", err.to_string()));
+        }
+        for line in FlavorLine::values() {
+            if let Some(Err(err)) = scripts
+                .get(line.name())
+                .map(|x| Formats::check_flavor_line(line.name(), x))
+            {
+                issues.push(LintIssue::new(line.file(), "*", err.to_string()));
+            }
+        }
+
+        let ancestry_names = self
+            .ancestries
+            .keys()
+            .map(|x| x.name().to_string())
+            .collect::<HashSet<_>>();
+        let heritage_names = self
+            .versatile_heritages
+            .keys()
+            .map(|x| x.name().to_string())
+            .collect::<HashSet<_>>();
+        let background_names = self
+            .backgrounds
+            .keys()
+            .map(|x| x.name().to_string())
+            .collect::<HashSet<_>>();
+        lint_sentences(
+            &mut issues,
+            &self.sentences,
+            &ancestry_names,
+            &heritage_names,
+            &background_names,
+        );
+        let mut known_traits: HashSet<Trait> = HashSet::new();
+
+        for ancestry in self.ancestries.keys() {
+            let name = ancestry.name();
+            known_traits.extend(ancestry.traits().iter().cloned());
+            known_traits.insert(Trait::new(ancestry.size().to_string()));
+
+            if let Some(x) = ancestry.possible_eye_colors() {
+                lint_weights(
+                    &mut issues,
+                    ANCESTRIES_FILE,
+                    &name,
+                    "possible_eye_colors",
+                    x,
+                );
+            }
+            if let Some(x) = ancestry.possible_hair_colors() {
+                lint_weights(
+                    &mut issues,
+                    ANCESTRIES_FILE,
+                    &name,
+                    "possible_hair_colors",
+                    x,
+                );
+            }
+            if let Some(x) = ancestry.possible_hair_length() {
+                lint_weights(
+                    &mut issues,
+                    ANCESTRIES_FILE,
+                    &name,
+                    "possible_hair_length",
+                    x,
+                );
+            }
+            if let Some(x) = ancestry.possible_hair_type() {
+                lint_weights(&mut issues, ANCESTRIES_FILE, &name, "possible_hair_type", x);
+            }
+            if let Some(x) = ancestry.specimen_surnames() {
+                lint_weights(&mut issues, ANCESTRIES_FILE, &name, "specimen_surnames", x);
+            }
+            lint_weights(
+                &mut issues,
+                ANCESTRIES_FILE,
+                &name,
+                "possible_skin_tone",
+                ancestry.possible_skin_tone(),
+            );
+            lint_weights(
+                &mut issues,
+                ANCESTRIES_FILE,
+                &name,
+                "possible_skin_texture",
+                ancestry.possible_skin_texture(),
+            );
+            lint_weights(
+                &mut issues,
+                ANCESTRIES_FILE,
+                &name,
+                "age_range_distribution",
+                ancestry.age_range_distribution(),
+            );
+            lint_age_ranges(&mut issues, &name, ancestry.age_ranges());
+            lint_face(&mut issues, ANCESTRIES_FILE, &name, ancestry.face());
+            if let Some(body) = ancestry.body().filter(|x| !x.builds.is_empty()) {
+                lint_weights(
+                    &mut issues,
+                    ANCESTRIES_FILE,
+                    &name,
+                    "body.builds",
+                    &body.builds,
+                );
+            }
+
+            if let Err(err) = ancestry.formats().check() {
+                issues.push(LintIssue::new(ANCESTRIES_FILE, &name, err.to_string()));
+            }
+            for line in ancestry.formats().lines.keys() {
+                if FlavorLine::from_name(line).is_none() {
+                    issues.push(LintIssue::new(
+                        ANCESTRIES_FILE,
+                        &name,
+                        format!("formats has a script for unknown line `{line}`"),
+                    ));
+                }
+            }
+
+            let name_traits = ancestry
+                .traits()
+                .iter()
+                .filter(|x| self.names.contains_key(*x))
+                .collect::<Vec<_>>();
+            if name_traits.is_empty() {
+                issues.push(LintIssue::new(
+                    ANCESTRIES_FILE,
+                    &name,
+                    "no trait of this ancestry has a name table",
+                ));
+            }
+            let sexes: &[&str] = if ancestry.is_asexual() {
+                &[""]
+            } else {
+                &GENERATED_SEXES
+            };
+            for name_trait in name_traits {
+                for sex in sexes {
+                    if !self.names[name_trait].contains_key(*sex) {
+                        issues.push(LintIssue::new(
+                            NAMES_FILE,
+                            name_trait,
+                            format!("no names for sex `{sex}` used by ancestry {name}"),
+                        ));
+                    }
+                }
+            }
+        }
+
+        for heritage in self.versatile_heritages.keys() {
+            let name = heritage.name();
+            known_traits.extend(heritage.traits().iter().cloned());
+
+            if let ValidAncestries::AllOf(x) | ValidAncestries::Only(x) =
+                heritage.valid_ancestries()
+            {
+                for ancestry in x.iter().filter(|x| !ancestry_names.contains(*x)) {
+                    issues.push(LintIssue::new(
+                        HERITAGES_FILE,
+                        &name,
+                        format!("valid_ancestries names unknown ancestry `{ancestry}`"),
+                    ));
+                }
+            }
+            lint_face(&mut issues, HERITAGES_FILE, &name, heritage.face());
+            if let Err(err) = heritage.formats().check() {
+                issues.push(LintIssue::new(HERITAGES_FILE, &name, err.to_string()));
+            }
+        }
+
+        lint_personality(
+            &mut issues,
+            &self.personality,
+            &background_names,
+            &known_traits,
+        );
+
+        for (name_trait, tables) in &self.names {
+            if !known_traits.contains(name_trait) {
+                issues.push(LintIssue::new(
+                    NAMES_FILE,
+                    name_trait,
+                    "no ancestry or heritage has this trait",
+                ));
+            }
+            for (sex, names) in tables {
+                lint_weights(
+                    &mut issues,
+                    NAMES_FILE,
+                    format!("{name_trait}/{sex}"),
+                    "name table",
+                    names,
+                );
+            }
+        }
+
+        issues.sort();
+        issues.dedup();
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> HashSet<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_lint_weights() {
+        let mut issues = Vec::new();
+        lint_weights::<String>(&mut issues, NAMES_FILE, "Elf", "male", &WeightMap::new());
+        let zero = WeightMap([("Aeryn".to_string(), 0)].into_iter().collect());
+        lint_weights(&mut issues, NAMES_FILE, "Elf", "female", &zero);
+        let fine = WeightMap([("Aeryn".to_string(), 1)].into_iter().collect());
+        lint_weights(&mut issues, NAMES_FILE, "Elf", "female", &fine);
+        assert_eq!(
+            vec![
+                LintIssue::new(NAMES_FILE, "Elf", "male is empty"),
+                LintIssue::new(NAMES_FILE, "Elf", "female has no positive weight"),
+            ],
+            issues
+        );
+    }
+
+    #[test]
+    fn test_lint_age_ranges() {
+        let mut issues = Vec::new();
+        let age_ranges = AgeRanges {
+            child: 1,
+            youth: 10,
+            adulthood: 10,
+            middle_age: 40,
+            old: 60,
+            venerable: 80,
+            lifespan: 70,
+        };
+        lint_age_ranges(&mut issues, "Human", &age_ranges);
+        assert_eq!(
+            vec![
+                LintIssue::new(
+                    ANCESTRIES_FILE,
+                    "Human",
+                    "age_ranges are not increasing: youth is 10, adulthood is 10"
+                ),
+                LintIssue::new(
+                    ANCESTRIES_FILE,
+                    "Human",
+                    "age_ranges are not increasing: venerable is 80, lifespan is 70"
+                ),
+            ],
+            issues
+        );
+    }
+
+    #[test]
+    fn test_lint_face() {
+        let face: FaceFeatures = ron::from_str(
+            r#"#![enable(unwrap_newtypes)]
+            (
+                noses: {"broad": 0},
+                facial_hair: [(text: "a beard", sexes: ["male", "none"])],
+                features: [(text: "tusks", chance: 1.5), (text: "freckles", chance: 0.5)],
+            )"#,
+        )
+        .unwrap();
+        let mut issues = Vec::new();
+        lint_face(&mut issues, HERITAGES_FILE, "Dromaar", &face);
+        assert_eq!(
+            vec![
+                LintIssue::new(
+                    HERITAGES_FILE,
+                    "Dromaar",
+                    "face.noses has no positive weight"
+                ),
+                LintIssue::new(
+                    HERITAGES_FILE,
+                    "Dromaar",
+                    "facial hair `a beard` names unknown sex `none`"
+                ),
+                LintIssue::new(
+                    HERITAGES_FILE,
+                    "Dromaar",
+                    "facial feature `tusks` has chance 1.5 outside of 0 to 1"
+                ),
+            ],
+            issues
+        );
+    }
+
+    #[test]
+    fn test_lint_sentences() {
+        let sentences: SentenceTemplates = ron::from_str(
+            r#"{
+                "face_line": [
+                    (text: "{Pronoun.subject} {pronoun.is} {lineage}.", weight: 0, ancestries: ["Elf"]),
+                    (text: "{x}", weight: 0, heritages: ["Aiuvarin", "Sylph"], backgrounds: ["Pirate"]),
+                ],
+                "lineage_line": [(text: "{Lineage}.")],
+                "nose_line": [(text: "A nose.")],
+            }"#,
+        )
+        .unwrap();
+        let mut issues = Vec::new();
+        lint_sentences(
+            &mut issues,
+            &sentences,
+            &names(&["Elf"]),
+            &names(&["Aiuvarin"]),
+            &names(&["Scholar"]),
+        );
+        issues.sort();
+        let issue = |line, message: &str| LintIssue::new(SENTENCES_FILE, line, message);
+        assert_eq!(
+            vec![
+                issue(
+                    "face_line",
+                    "`{Pronoun.subject} {pronoun.is} {lineage}.` uses unknown slot `lineage`"
+                ),
+                issue("face_line", "`{x}` names unknown background `Pirate`"),
+                issue("face_line", "`{x}` names unknown heritage `Sylph`"),
+                issue("face_line", "`{x}` uses unknown slot `x`"),
+                issue("face_line", "no template has a positive weight"),
+                issue("nose_line", "unknown line"),
+            ],
+            issues
+        );
+    }
+
+    #[test]
+    fn test_lint_personality() {
+        let personality: Personality = ron::from_str(
+            r#"#![enable(unwrap_newtypes)]
+            (
+                likes: [(text: "rum", weight: 0, backgrounds: ["Pirate"], traits: ["Dwarf", "Orc"])],
+                habits: [(text: "whistle", backgrounds: ["Scholar"])],
+            )"#,
+        )
+        .unwrap();
+        let mut issues = Vec::new();
+        lint_personality(
+            &mut issues,
+            &personality,
+            &names(&["Scholar"]),
+            &[Trait::new("Dwarf")].into_iter().collect(),
+        );
+        assert_eq!(
+            vec![
+                LintIssue::new(PERSONALITY_FILE, "like", "no entry has a positive weight"),
+                LintIssue::new(
+                    PERSONALITY_FILE,
+                    "like",
+                    "`rum` names unknown background `Pirate`"
+                ),
+                LintIssue::new(
+                    PERSONALITY_FILE,
+                    "like",
+                    "`rum` names trait `Orc` no ancestry or heritage has"
+                ),
+            ],
+            issues
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Reading generator data packs from directories, zip files or memory.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use log::{error, info};
+use thiserror::Error;
+
+use crate::{
+    formats::FlavorLine,
+    generators::{
+        GeneratorData, GeneratorScripts, ScriptValidationError, FLAVOR_DESCRIPTION_LINE_SCRIPT,
+    },
+    weight_presets::WeightPreset,
+    DataOverlay, ManifestError, PackManifest, MANIFEST_FILE,
+};
+
+/// Probability that a generated NPC has no versatile heritage, used when
+/// the manifest doesn't set one.
+pub const DEFAULT_NORMAL_HERITAGE_WEIGHT: f64 = 0.8;
+
+const ANCESTRIES_FILE: &str = "ancestries.ron";
+const HERITAGES_FILE: &str = "heritages.ron";
+const BACKGROUNDS_FILE: &str = "backgrounds.ron";
+const NAMES_FILE: &str = "names.ron";
+const ARCHETYPES_FILE: &str = "archetypes.ron";
+const OVERLAY_FILE: &str = "overlay.ron";
+const SENTENCES_FILE: &str = "sentences.ron";
+const PERSONALITY_FILE: &str = "personality.ron";
+const FLAVOR_DESCRIPTION_LINE_SCRIPT_FILE: &str =
+    "scripts/default_format_flavor_description_line.glu";
+
+#[derive(Error, Debug)]
+pub enum LoadError {
+    #[error("can't read {file} from {source_name}: {error}")]
+    Io {
+        source_name: String,
+        file: String,
+        error: io::Error,
+    },
+    #[error("can't open {source_name}: {error}")]
+    Open {
+        source_name: String,
+        error: io::Error,
+    },
+    #[error("{source_name} has no {file}")]
+    MissingFile { source_name: String, file: String },
+    #[error("can't deserialize {file} from {source_name}: {error}")]
+    Deserialize {
+        source_name: String,
+        file: String,
+        error: Box<ron::error::SpannedError>,
+    },
+    #[cfg(feature = "zip")]
+    #[error("can't read zip file {source_name}: {error}")]
+    Zip {
+        source_name: String,
+        error: zip::result::ZipError,
+    },
+    #[error("no data packs given")]
+    NoPacks,
+    #[error(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error(transparent)]
+    Scripts(#[from] ScriptValidationError),
+}
+
+/// Where the files of a data pack come from.
+///
+/// File names are relative to the pack root, e.g. `ancestries.ron` or
+/// `scripts/default_format_flavor_description_line.glu`.
+pub trait DataSource {
+    /// A name for the source in error messages, e.g. its path.
+    fn name(&self) -> String;
+
+    /// Reads a file of the pack, `None` if the pack doesn't contain it.
+    fn read(&mut self, file: &str) -> Result<Option<String>, LoadError>;
+}
+
+/// A data pack unpacked into a directory.
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl DataSource for DirectorySource {
+    fn name(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn read(&mut self, file: &str) -> Result<Option<String>, LoadError> {
+        let path = self.root.join(file);
+        info!("Trying to read {path:?}");
+        match fs::read_to_string(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(LoadError::Io {
+                source_name: self.name(),
+                file: file.to_string(),
+                error,
+            }),
+        }
+    }
+}
+
+/// A data pack in a zip file, with the data files in the root of the archive.
+#[cfg(feature = "zip")]
+pub struct ZipSource<R: io::Read + io::Seek> {
+    source_name: String,
+    archive: zip::ZipArchive<R>,
+}
+
+#[cfg(feature = "zip")]
+impl ZipSource<fs::File> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let path = path.as_ref();
+        let source_name = path.display().to_string();
+        let file = fs::File::open(path).map_err(|error| LoadError::Open {
+            source_name: source_name.clone(),
+            error,
+        })?;
+        Self::new(source_name, file)
+    }
+}
+
+#[cfg(feature = "zip")]
+impl<R: io::Read + io::Seek> ZipSource<R> {
+    pub fn new(source_name: impl AsRef<str>, reader: R) -> Result<Self, LoadError> {
+        let source_name = source_name.as_ref().to_string();
+        match zip::ZipArchive::new(reader) {
+            Ok(archive) => Ok(Self {
+                source_name,
+                archive,
+            }),
+            Err(error) => Err(LoadError::Zip { source_name, error }),
+        }
+    }
+}
+
+#[cfg(feature = "zip")]
+impl<R: io::Read + io::Seek> DataSource for ZipSource<R> {
+    fn name(&self) -> String {
+        self.source_name.clone()
+    }
+
+    fn read(&mut self, file: &str) -> Result<Option<String>, LoadError> {
+        let entry = match self.archive.by_name(file) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(error) => {
+                return Err(LoadError::Zip {
+                    source_name: self.source_name.clone(),
+                    error,
+                })
+            }
+        };
+        io::read_to_string(entry)
+            .map(Some)
+            .map_err(|error| LoadError::Io {
+                source_name: self.source_name.clone(),
+                file: file.to_string(),
+                error,
+            })
+    }
+}
+
+/// A data pack held in memory, keyed by file name.
+#[derive(Default, Debug, Clone)]
+pub struct MemorySource {
+    source_name: String,
+    files: HashMap<String, String>,
+}
+
+impl MemorySource {
+    pub fn new(source_name: impl AsRef<str>) -> Self {
+        Self {
+            source_name: source_name.as_ref().to_string(),
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, file: impl AsRef<str>, contents: impl AsRef<str>) {
+        self.files
+            .insert(file.as_ref().to_string(), contents.as_ref().to_string());
+    }
+}
+
+impl DataSource for MemorySource {
+    fn name(&self) -> String {
+        self.source_name.clone()
+    }
+
+    fn read(&mut self, file: &str) -> Result<Option<String>, LoadError> {
+        Ok(self.files.get(file).cloned())
+    }
+}
+
+/// Reads the manifest of a pack, packs without one are schema version 1.
+pub fn read_manifest(source: &mut dyn DataSource) -> Result<PackManifest, LoadError> {
+    let manifest = match source.read(MANIFEST_FILE)? {
+        Some(data) => ron::from_str(&data).map_err(|error| LoadError::Deserialize {
+            source_name: source.name(),
+            file: MANIFEST_FILE.to_string(),
+            error: Box::new(error),
+        })?,
+        None => PackManifest::legacy(source.name()),
+    };
+    manifest.check_compatibility()?;
+    Ok(manifest)
+}
+
+fn read_required(source: &mut dyn DataSource, file: &str) -> Result<String, LoadError> {
+    source.read(file)?.ok_or_else(|| LoadError::MissingFile {
+        source_name: source.name(),
+        file: file.to_string(),
+    })
+}
+
+fn deserialize<T>(
+    source: &dyn DataSource,
+    manifest: &PackManifest,
+    file: &str,
+    data: &str,
+) -> Result<T, LoadError>
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    ron::from_str(&manifest.migrate(file, data)).map_err(|error| LoadError::Deserialize {
+        source_name: source.name(),
+        file: file.to_string(),
+        error: Box::new(error),
+    })
+}
+
+fn read_ron<T>(
+    source: &mut dyn DataSource,
+    manifest: &PackManifest,
+    file: &str,
+) -> Result<T, LoadError>
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    let data = read_required(source, file)?;
+    deserialize(source, manifest, file, &data)
+}
+
+fn read_optional_ron<T>(
+    source: &mut dyn DataSource,
+    manifest: &PackManifest,
+    file: &str,
+) -> Result<T, LoadError>
+where
+    T: for<'a> serde::Deserialize<'a> + Default,
+{
+    match source.read(file)? {
+        Some(data) => deserialize(source, manifest, file, &data),
+        None => Ok(T::default()),
+    }
+}
+
+/// Reads the [`FlavorLine`] scripts the pack contains.
+fn read_flavor_line_scripts(source: &mut dyn DataSource) -> Result<GeneratorScripts, LoadError> {
+    let mut scripts = GeneratorScripts::default();
+    for line in FlavorLine::values() {
+        if let Some(script) = source.read(line.file())? {
+            scripts.insert(line.name(), script);
+        }
+    }
+    Ok(scripts)
+}
+
+/// Reads a complete data pack without checking its scripts.
+pub fn read_generator_data(
+    source: &mut dyn DataSource,
+) -> Result<(GeneratorData, GeneratorScripts), LoadError> {
+    info!("Loading generator data from {}", source.name());
+    let manifest = read_manifest(source)?;
+
+    let mut archetypes: Vec<crate::Archetype> = read_ron(source, &manifest, ARCHETYPES_FILE)?;
+    archetypes.sort_by_key(|x| x.level());
+
+    let generator_data = GeneratorData {
+        ancestries: read_ron(source, &manifest, ANCESTRIES_FILE)?,
+        normal_heritage_weight: manifest
+            .normal_heritage_weight
+            .unwrap_or(DEFAULT_NORMAL_HERITAGE_WEIGHT),
+        versatile_heritages: read_ron(source, &manifest, HERITAGES_FILE)?,
+        heritages: Default::default(),
+        backgrounds: read_ron(source, &manifest, BACKGROUNDS_FILE)?,
+        names: read_ron(source, &manifest, NAMES_FILE)?,
+        archetypes,
+        sentences: read_optional_ron(source, &manifest, SENTENCES_FILE)?,
+        personality: read_optional_ron(source, &manifest, PERSONALITY_FILE)?,
+    };
+
+    info!("Reading scripts...");
+    let mut generator_scripts = read_flavor_line_scripts(source)?;
+    generator_scripts.insert(
+        FLAVOR_DESCRIPTION_LINE_SCRIPT,
+        read_required(source, FLAVOR_DESCRIPTION_LINE_SCRIPT_FILE)?,
+    );
+
+    Ok((generator_data, generator_scripts))
+}
+
+/// Reads a complete data pack and typechecks its scripts.
+pub fn load_generator_data(
+    source: &mut dyn DataSource,
+) -> Result<(GeneratorData, GeneratorScripts), LoadError> {
+    let (generator_data, generator_scripts) = read_generator_data(source)?;
+    generator_data.validate_scripts(&generator_scripts)?;
+    Ok((generator_data, generator_scripts))
+}
+
+/// A pack stacked on top of other packs. Every file is optional, so a
+/// homebrew pack only has to contain what it changes.
+pub struct DataLayer {
+    pub manifest: PackManifest,
+    pub data: GeneratorData,
+    /// The scripts of the layer, replacing those of the same name below
+    pub scripts: GeneratorScripts,
+    pub overlay: DataOverlay,
+}
+
+pub fn read_data_layer(source: &mut dyn DataSource) -> Result<DataLayer, LoadError> {
+    let manifest = read_manifest(source)?;
+    let data = GeneratorData {
+        ancestries: read_optional_ron(source, &manifest, ANCESTRIES_FILE)?,
+        versatile_heritages: read_optional_ron(source, &manifest, HERITAGES_FILE)?,
+        backgrounds: read_optional_ron(source, &manifest, BACKGROUNDS_FILE)?,
+        names: read_optional_ron(source, &manifest, NAMES_FILE)?,
+        archetypes: read_optional_ron(source, &manifest, ARCHETYPES_FILE)?,
+        sentences: read_optional_ron(source, &manifest, SENTENCES_FILE)?,
+        personality: read_optional_ron(source, &manifest, PERSONALITY_FILE)?,
+        ..Default::default()
+    };
+    let mut scripts = read_flavor_line_scripts(source)?;
+    if let Some(script) = source.read(FLAVOR_DESCRIPTION_LINE_SCRIPT_FILE)? {
+        scripts.insert(FLAVOR_DESCRIPTION_LINE_SCRIPT, script);
+    }
+    let overlay = read_optional_ron(source, &manifest, OVERLAY_FILE)?;
+    Ok(DataLayer {
+        manifest,
+        data,
+        scripts,
+        overlay,
+    })
+}
+
+/// Loads the first pack completely and stacks the others on top of it in order.
+pub fn load_layered_generator_data(
+    sources: &mut [Box<dyn DataSource>],
+) -> Result<(GeneratorData, GeneratorScripts), LoadError> {
+    let (base, layers) = sources.split_first_mut().ok_or(LoadError::NoPacks)?;
+    let mut manifests = vec![read_manifest(base.as_mut())?];
+    manifests[0].check_dependencies(&[])?;
+    let (mut generator_data, mut generator_scripts) = read_generator_data(base.as_mut())?;
+
+    for layer in layers {
+        info!("Stacking data pack {}", layer.name());
+        let layer = read_data_layer(layer.as_mut())?;
+        layer.manifest.check_dependencies(&manifests)?;
+        generator_data.merge(layer.data);
+        generator_data.apply_overlay(&layer.overlay);
+        generator_scripts.extend(layer.scripts);
+        if let Some(weight) = layer.manifest.normal_heritage_weight {
+            generator_data.normal_heritage_weight = weight;
+        }
+        manifests.push(layer.manifest);
+    }
+
+    generator_data.validate_scripts(&generator_scripts)?;
+    Ok((generator_data, generator_scripts))
+}
+
+/// Reads every `.ron` weight preset in `directory`. Files that can't be
+/// read are logged and skipped.
+pub fn load_weight_presets(directory: &Path) -> Result<Vec<Arc<WeightPreset>>, LoadError> {
+    let io_error = |error| LoadError::Open {
+        source_name: directory.display().to_string(),
+        error,
+    };
+    let mut weight_presets = Vec::new();
+    for entry in directory.read_dir().map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.is_dir() {
+            info!("Skipping directory {path:?}");
+            continue;
+        }
+        if !path.extension().map(|x| x == "ron").unwrap_or(false) {
+            error!("Unknown file: {path:?}");
+            continue;
+        }
+
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        match fs::read_to_string(&path) {
+            Ok(data) => match ron::from_str(&data) {
+                Ok(weight_preset) => weight_presets.push(Arc::new(weight_preset)),
+                Err(x) => error!("Can't deserialize {file}: {x}"),
+            },
+            Err(x) => error!("Can't read {file}: {x}"),
+        }
+    }
+    Ok(weight_presets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_from_memory() {
+        let mut source = MemorySource::new("test pack");
+        source.insert(
+            MANIFEST_FILE,
+            "(name: \"test\", version: \"1\", schema_version: 2, normal_heritage_weight: Some(0.5))",
+        );
+        source.insert(ANCESTRIES_FILE, "({})");
+        source.insert(HERITAGES_FILE, "({})");
+        source.insert(BACKGROUNDS_FILE, "({})");
+        source.insert(NAMES_FILE, "{}");
+        source.insert(ARCHETYPES_FILE, "[]");
+
+        assert!(matches!(
+            read_generator_data(&mut source),
+            Err(LoadError::MissingFile { .. })
+        ));
+
+        source.insert(FLAVOR_DESCRIPTION_LINE_SCRIPT_FILE, "\\x -> x");
+        let (generator_data, generator_scripts) = read_generator_data(&mut source).unwrap();
+        assert_eq!(0.5, generator_data.normal_heritage_weight);
+        assert_eq!("\\x -> x", generator_scripts.flavor_description_line());
+        assert_eq!(None, generator_scripts.get(FlavorLine::Face.name()));
+
+        source.insert(FlavorLine::Face.file(), "\\npc line -> line");
+        let (_, generator_scripts) = read_generator_data(&mut source).unwrap();
+        assert_eq!(
+            Some("\\npc line -> line"),
+            generator_scripts.get(FlavorLine::Face.name())
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Schema version of the data files this version of the generator reads.
+///
+/// Version 1 are the packs from before manifests existed.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// Name of the manifest file in the root of a data pack.
+pub const MANIFEST_FILE: &str = "manifest.ron";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ManifestError {
+    #[error(
+        "pack `{pack}` uses schema version {version}, supported are 1 to {CURRENT_SCHEMA_VERSION}"
+    )]
+    UnsupportedSchemaVersion { pack: String, version: u32 },
+    #[error("pack `{pack}` depends on `{dependency}`, which isn't loaded before it")]
+    MissingDependency { pack: String, dependency: String },
+    #[error("pack `{pack}` depends on `{dependency}` version {required}, but {found} is loaded")]
+    DependencyVersionMismatch {
+        pack: String,
+        dependency: String,
+        required: String,
+        found: String,
+    },
+    #[error("pack `{pack}` lists a checksum for {file}, but has no such file")]
+    MissingChecksummedFile { pack: String, file: String },
+    #[error("checksum of {file} doesn't match the manifest of pack `{pack}`")]
+    ChecksumMismatch { pack: String, file: String },
+}
+
+/// Hex encoded SHA-256 of a data file, as listed in [`PackManifest::checksums`].
+pub fn checksum(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PackDependency {
+    pub name: String,
+    /// The exact version needed, any version if not given
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// Describes a data pack, read from its `manifest.ron`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PackManifest {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    pub schema_version: u32,
+    #[serde(default)]
+    pub dependencies: Vec<PackDependency>,
+    /// Probability that a generated NPC has no versatile heritage
+    #[serde(default)]
+    pub normal_heritage_weight: Option<f64>,
+    /// Checksums of the data files, by their path inside the pack
+    #[serde(default)]
+    pub checksums: BTreeMap<String, String>,
+}
+
+impl PackManifest {
+    /// The manifest assumed for packs without one.
+    pub fn legacy(name: impl AsRef<str>) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            version: String::from("0"),
+            author: None,
+            license: None,
+            schema_version: 1,
+            dependencies: Vec::new(),
+            normal_heritage_weight: None,
+            checksums: BTreeMap::new(),
+        }
+    }
+
+    pub fn check_compatibility(&self) -> Result<(), ManifestError> {
+        if (1..=CURRENT_SCHEMA_VERSION).contains(&self.schema_version) {
+            Ok(())
+        } else {
+            Err(ManifestError::UnsupportedSchemaVersion {
+                pack: self.name.clone(),
+                version: self.schema_version,
+            })
+        }
+    }
+
+    /// Checks that every dependency is among the packs loaded before this one.
+    pub fn check_dependencies(&self, loaded: &[PackManifest]) -> Result<(), ManifestError> {
+        for dependency in &self.dependencies {
+            let found = loaded
+                .iter()
+                .find(|x| x.name == dependency.name)
+                .ok_or_else(|| ManifestError::MissingDependency {
+                    pack: self.name.clone(),
+                    dependency: dependency.name.clone(),
+                })?;
+            if let Some(ref required) = dependency.version {
+                if *required != found.version {
+                    return Err(ManifestError::DependencyVersionMismatch {
+                        pack: self.name.clone(),
+                        dependency: dependency.name.clone(),
+                        required: required.clone(),
+                        found: found.version.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks `contents` against the checksum of `file`, files without one are fine.
+    pub fn verify_checksum(&self, file: &str, contents: &[u8]) -> Result<(), ManifestError> {
+        match self.checksums.get(file) {
+            Some(expected) if *expected != checksum(contents) => {
+                Err(ManifestError::ChecksumMismatch {
+                    pack: self.name.clone(),
+                    file: file.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Rewrites a data file of this pack into the current schema version.
+    pub fn migrate<'a>(&self, file: &str, contents: &'a str) -> Cow<'a, str> {
+        let mut contents = Cow::Borrowed(contents);
+        for (version, migration) in MIGRATIONS {
+            if self.schema_version < *version {
+                if let Some(migrated) = migration(file, &contents) {
+                    contents = Cow::Owned(migrated);
+                }
+            }
+        }
+        contents
+    }
+}
+
+/// Migration to a schema version, returns `None` if the file is unchanged.
+type Migration = fn(file: &str, contents: &str) -> Option<String>;
+
+/// Migrations in ascending order, each one applied to files older than its version.
+const MIGRATIONS: &[(u32, Migration)] = &[(2, migrate_to_2)];
+
+/// Version 2 fixed the spelling of the gargantuan size, only the values of
+/// `size` fields are rewritten.
+fn migrate_to_2(file: &str, contents: &str) -> Option<String> {
+    const MISSPELLED: &str = "Garganutan";
+    if file != "ancestries.ron" {
+        return None;
+    }
+    let mut migrated = String::with_capacity(contents.len());
+    let mut rest = contents;
+    while let Some(index) = rest.find(MISSPELLED) {
+        let (before, after) = (&rest[..index], &rest[index + MISSPELLED.len()..]);
+        let is_size = before.trim_end().strip_suffix(':').is_some_and(|x| {
+            x.trim_end()
+                .strip_suffix("size")
+                .is_some_and(|x| !x.ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+        }) && !after.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+        migrated.push_str(before);
+        migrated.push_str(if is_size { "Gargantuan" } else { MISSPELLED });
+        rest = after;
+    }
+    migrated.push_str(rest);
+    (migrated != contents).then_some(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_legacy_size() {
+        let legacy = PackManifest::legacy("old pack");
+        assert_eq!(
+            "(size: Gargantuan)",
+            legacy.migrate("ancestries.ron", "(size: Garganutan)")
+        );
+        let unrelated = r#"(name: "Garganutan", size: Garganutans, shoe_size: Garganutan)"#;
+        assert!(matches!(
+            legacy.migrate("ancestries.ron", unrelated),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            r#"(name: "Garganutan", size :
+                Gargantuan)"#,
+            legacy.migrate(
+                "ancestries.ron",
+                r#"(name: "Garganutan", size :
+                Garganutan)"#
+            )
+        );
+
+        let current = PackManifest {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            ..legacy
+        };
+        assert!(matches!(
+            current.migrate("ancestries.ron", "(size: Gargantuan)"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_check_dependencies() {
+        let core = PackManifest {
+            version: "1.2".into(),
+            schema_version: CURRENT_SCHEMA_VERSION,
+            ..PackManifest::legacy("core")
+        };
+        let mut homebrew = PackManifest {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            dependencies: vec![PackDependency {
+                name: "core".into(),
+                version: Some("1.2".into()),
+            }],
+            ..PackManifest::legacy("homebrew")
+        };
+        assert_eq!(
+            Ok(()),
+            homebrew.check_dependencies(std::slice::from_ref(&core))
+        );
+        assert!(homebrew.check_dependencies(&[]).is_err());
+
+        homebrew.dependencies[0].version = Some("2.0".into());
+        assert!(homebrew.check_dependencies(&[core]).is_err());
+        assert!(PackManifest {
+            schema_version: CURRENT_SCHEMA_VERSION + 1,
+            ..homebrew
+        }
+        .check_compatibility()
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let mut manifest = PackManifest::legacy("pack");
+        manifest
+            .checksums
+            .insert("names.ron".into(), checksum(b"{}"));
+
+        assert_eq!(Ok(()), manifest.verify_checksum("names.ron", b"{}"));
+        assert_eq!(Ok(()), manifest.verify_checksum("ancestries.ron", b"()"));
+        assert_eq!(
+            Err(ManifestError::ChecksumMismatch {
+                pack: "pack".into(),
+                file: "names.ron".into()
+            }),
+            manifest.verify_checksum("names.ron", b"{ }")
+        );
+    }
+}
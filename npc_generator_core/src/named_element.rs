@@ -4,6 +4,6 @@ use crate::Trait;
 
 pub trait NamedElement {
     fn traits(&self) -> &[Trait];
-    fn name(&self) -> Cow<str>;
-    fn formatted_name(&self) -> Cow<str>;
+    fn name(&self) -> Cow<'_, str>;
+    fn formatted_name(&self) -> Cow<'_, str>;
 }
@@ -22,10 +22,10 @@ impl<K: Hash + Eq> WeightMap<K> {
         Self(HashMap::new())
     }
     #[inline]
-    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&Weight>
+    pub fn get<Q>(&self, k: &Q) -> Option<&Weight>
     where
         K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         self.0.get(k)
     }
@@ -1,4 +1,4 @@
-use crate::{AgeRange, Ancestry, Archetype, Background, Heritage};
+use crate::{AgeRange, Ancestry, Archetype, Background, Heritage, Units};
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -10,4 +10,5 @@ pub struct NpcOptions {
     pub age_range: Option<AgeRange>,
     pub sex: Option<String>,
     pub enable_flavor_text: bool,
+    pub units: Units,
 }
@@ -0,0 +1,279 @@
+use std::fmt::{Display, Write};
+
+use crate::{AbilityStats, NamedElement, Statblock};
+
+/// A markdown note for Obsidian with YAML frontmatter and a `statblock`
+/// block that the Fantasy Statblocks plugin can render.
+pub struct ObsidianNote(Statblock);
+
+impl Statblock {
+    pub fn into_obsidian_note(self) -> ObsidianNote {
+        ObsidianNote(self)
+    }
+    pub fn as_obsidian_note(&self) -> ObsidianNote {
+        ObsidianNote(self.clone())
+    }
+}
+
+/// Quotes a string as a YAML double quoted scalar.
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn yaml_list<'a>(values: impl IntoIterator<Item = &'a str>) -> String {
+    let values = values.into_iter().map(yaml_string).collect::<Vec<_>>();
+    format!("[{}]", values.join(", "))
+}
+
+/// Turns a trait or ancestry name into an Obsidian tag.
+fn tag(value: &str) -> String {
+    value
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+impl ObsidianNote {
+    /// A file name derived from the NPC name, without characters that
+    /// Obsidian doesn't allow in note names.
+    pub fn file_stem(&self) -> String {
+        let stem: String = self
+            .0
+            .name()
+            .chars()
+            .filter(|c| {
+                !matches!(
+                    c,
+                    '[' | ']' | '#' | '^' | '|' | '\\' | '/' | ':' | '?' | '*' | '<' | '>' | '"'
+                )
+            })
+            .collect();
+        let stem = stem.trim();
+        if stem.is_empty() {
+            String::from("NPC")
+        } else {
+            stem.to_string()
+        }
+    }
+
+    fn sorted_traits(&self) -> Vec<String> {
+        let mut traits = self
+            .0
+            .traits()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        traits.sort();
+        traits
+    }
+
+    fn frontmatter(&self) -> Result<String, std::fmt::Error> {
+        let statblock = &self.0;
+        let mut frontmatter = String::new();
+        writeln!(frontmatter, "---")?;
+        writeln!(frontmatter, "name: {}", yaml_string(&statblock.name()))?;
+        if let Some(ancestry) = statblock.ancestry() {
+            writeln!(frontmatter, "ancestry: {}", yaml_string(&ancestry.name()))?;
+        }
+        if let Some(heritage) = statblock.heritage() {
+            writeln!(frontmatter, "heritage: {}", yaml_string(&heritage.name()))?;
+            if let Some(lineage) = heritage.lineage() {
+                writeln!(frontmatter, "lineage: {}", yaml_string(lineage))?;
+            }
+        }
+        writeln!(
+            frontmatter,
+            "background: {}",
+            yaml_string(statblock.class())
+        )?;
+        writeln!(frontmatter, "age: {}", statblock.age())?;
+        writeln!(
+            frontmatter,
+            "age_range: {}",
+            yaml_string(&statblock.age_range().to_string())
+        )?;
+        if !statblock.sex().is_empty() {
+            writeln!(frontmatter, "sex: {}", yaml_string(statblock.sex()))?;
+        }
+        writeln!(frontmatter, "level: {}", statblock.level())?;
+
+        let mut tags = vec![String::from("npc")];
+        tags.extend(self.sorted_traits().iter().map(|x| tag(x)));
+        writeln!(
+            frontmatter,
+            "tags: {}",
+            yaml_list(tags.iter().map(String::as_str))
+        )?;
+
+        let prd_references = statblock
+            .ancestry()
+            .and_then(|x| x.prd_reference())
+            .into_iter()
+            .chain(statblock.heritage().and_then(|x| x.prd_reference()))
+            .collect::<Vec<_>>();
+        if !prd_references.is_empty() {
+            writeln!(frontmatter, "prd: {}", yaml_list(prd_references))?;
+        }
+        writeln!(frontmatter, "---")?;
+        Ok(frontmatter)
+    }
+
+    fn statblock(&self) -> Result<String, std::fmt::Error> {
+        let statblock = &self.0;
+        let mut block = String::new();
+        writeln!(block, "```statblock")?;
+        writeln!(block, "layout: Basic Pathfinder 2e Layout")?;
+        writeln!(block, "source: pf2e_npc_generator")?;
+        writeln!(block, "name: {}", yaml_string(&statblock.name()))?;
+        writeln!(
+            block,
+            "level: {}",
+            yaml_string(&format!("{} {}", statblock.class(), statblock.level()))
+        )?;
+        writeln!(block, "rare_01: \"Unique\"")?;
+        for (index, trait_value) in self.sorted_traits().iter().enumerate() {
+            writeln!(
+                block,
+                "trait_{:02}: {}",
+                index + 1,
+                yaml_string(trait_value)
+            )?;
+        }
+        writeln!(block, "modifier: {}", statblock.perception())?;
+        writeln!(block, "perception:")?;
+        writeln!(block, "  - name: \"Perception\"")?;
+        writeln!(
+            block,
+            "    desc: {}",
+            yaml_string(&format!("{:+}", statblock.perception()))
+        )?;
+        writeln!(block, "languages: \"Common\"")?;
+
+        let mut skills = statblock
+            .skills()
+            .iter()
+            .filter(|x| x.1 != 0)
+            .collect::<Vec<_>>();
+        skills.sort_by_key(|x| x.0.to_string());
+        writeln!(block, "skills:")?;
+        writeln!(block, "  - name: \"Skills\"")?;
+        writeln!(
+            block,
+            "    desc: {}",
+            yaml_string(
+                &skills
+                    .iter()
+                    .map(|(skill, value)| format!("{skill}: {value:+}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        )?;
+
+        let AbilityStats {
+            strength,
+            dexterity,
+            constitution,
+            intelligence,
+            wisdom,
+            charisma,
+        } = statblock.attributes().clone();
+        writeln!(
+            block,
+            "abilityMods: [{strength}, {dexterity}, {constitution}, {intelligence}, {wisdom}, {charisma}]"
+        )?;
+        if !statblock.items().is_empty() {
+            writeln!(
+                block,
+                "items: {}",
+                yaml_list(statblock.items().iter().map(|x| x.as_ref()))
+            )?;
+        }
+        writeln!(block, "armorclass:")?;
+        writeln!(block, "  - name: \"AC\"")?;
+        writeln!(
+            block,
+            "    desc: {}",
+            yaml_string(&format!(
+                "{}; __Fort__ {:+}, __Ref__ {:+}, __Will__ {:+}",
+                statblock.armor_class(),
+                statblock.fortitude_save(),
+                statblock.reflex_save(),
+                statblock.will_save()
+            ))
+        )?;
+        writeln!(block, "health:")?;
+        writeln!(block, "  - name: \"HP\"")?;
+        writeln!(
+            block,
+            "    desc: {}",
+            yaml_string(&statblock.hit_points().to_string())
+        )?;
+        writeln!(
+            block,
+            "speed: {}",
+            yaml_string(&format!("{} feet", statblock.land_speed()))
+        )?;
+        writeln!(block, "```")?;
+        Ok(block)
+    }
+}
+
+impl Display for ObsidianNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.frontmatter()?)?;
+        writeln!(f)?;
+        write!(f, "{}", self.statblock()?)?;
+        writeln!(f)?;
+        write!(f, "{}", self.0.flavor())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stem() {
+        let stem = |name: &str| {
+            let mut statblock = Statblock::default();
+            statblock.set_name(name);
+            statblock.into_obsidian_note().file_stem()
+        };
+        assert_eq!("Amiri", stem("Amiri"));
+        assert_eq!("Kyra the Cleric", stem(" Kyra: the [Cleric]? "));
+        assert_eq!("ab", stem("a/b\\|#^*<>\""));
+        assert_eq!("NPC", stem(" ?? "));
+    }
+
+    #[test]
+    fn test_yaml_string() {
+        assert_eq!(r#""Lini""#, yaml_string("Lini"));
+        assert_eq!(r#""say \"hi\"""#, yaml_string(r#"say "hi""#));
+        assert_eq!(r#""a\\b""#, yaml_string(r"a\b"));
+        assert_eq!(
+            r#""one\ntwo\tthree\u0007""#,
+            yaml_string("one\ntwo\tthree\x07")
+        );
+        assert_eq!(
+            r#""key: value # not a comment""#,
+            yaml_string("key: value # not a comment")
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::{borrow::Cow, collections::HashMap, hash::Hash};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{generators::GeneratorData, NamedElement, WeightMap};
+
+/// Entries a data pack removes from the packs below it, by name.
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OverlayRemovals {
+    pub ancestries: Vec<String>,
+    pub heritages: Vec<String>,
+    pub backgrounds: Vec<String>,
+    /// Name tables, by trait
+    pub names: Vec<String>,
+    pub archetypes: Vec<String>,
+}
+
+/// New weights for entries of the packs below, by name.
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OverlayWeights {
+    pub ancestries: HashMap<String, u32>,
+    pub heritages: HashMap<String, u32>,
+    pub backgrounds: HashMap<String, u32>,
+}
+
+/// The `overlay.ron` of a data pack that is stacked on top of other packs.
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DataOverlay {
+    pub remove: OverlayRemovals,
+    pub weights: OverlayWeights,
+}
+
+/// Inserts every entry of `layer`, replacing entries with the same name.
+fn merge_weights<K: Hash + Eq>(base: &mut WeightMap<K>, layer: WeightMap<K>) {
+    for (key, weight) in layer {
+        // Removing first, since inserting an equal key keeps the old one
+        base.0.remove(&key);
+        base.0.insert(key, weight);
+    }
+}
+
+fn remove_named<K: Hash + Eq + NamedElement>(map: &mut WeightMap<K>, file: &str, names: &[String]) {
+    for name in names {
+        if !map.keys().any(|x| x.name() == name.as_str()) {
+            warn!("Overlay removes unknown entry `{name}` from {file}");
+        }
+    }
+    map.0
+        .retain(|key, _| !names.iter().any(|x| key.name() == x.as_str()));
+}
+
+fn set_weights<K: Hash + Eq + NamedElement>(
+    map: &mut WeightMap<K>,
+    file: &str,
+    weights: &HashMap<String, u32>,
+) {
+    for name in weights.keys() {
+        if !map.keys().any(|x| x.name() == name.as_str()) {
+            warn!("Overlay sets the weight of unknown entry `{name}` in {file}");
+        }
+    }
+    for (key, weight) in map.iter_mut() {
+        if let Some(new_weight) = weights.get(key.name().as_ref()) {
+            *weight = *new_weight;
+        }
+    }
+}
+
+impl GeneratorData {
+    /// Stacks `layer` on top of this data.
+    ///
+    /// Ancestries, heritages, backgrounds and archetypes of the layer replace
+    /// entries with the same name, name tables are merged per trait and sex.
+    /// The sentence templates of a line replace all templates of that line,
+    /// personality entries replace entries with the same text.
+    pub fn merge(&mut self, layer: GeneratorData) {
+        merge_weights(&mut self.ancestries, layer.ancestries);
+        merge_weights(&mut self.versatile_heritages, layer.versatile_heritages);
+        merge_weights(&mut self.backgrounds, layer.backgrounds);
+        for (name_trait, weights) in layer.heritages {
+            merge_weights(self.heritages.entry(name_trait).or_default(), weights);
+        }
+        for (name_trait, tables) in layer.names {
+            let base_tables = self.names.entry(name_trait).or_default();
+            for (sex, names) in tables {
+                merge_weights(base_tables.entry(sex).or_default(), names);
+            }
+        }
+        for archetype in layer.archetypes {
+            self.archetypes.retain(|x| x.name() != archetype.name());
+            self.archetypes.push(archetype);
+        }
+        self.archetypes.sort_by_key(|x| x.level());
+        self.sentences.0.extend(layer.sentences.0);
+        self.personality.merge(layer.personality);
+    }
+
+    /// Removes entries and adjusts weights as the overlay says.
+    pub fn apply_overlay(&mut self, overlay: &DataOverlay) {
+        let remove = &overlay.remove;
+        remove_named(&mut self.ancestries, "ancestries", &remove.ancestries);
+        remove_named(
+            &mut self.versatile_heritages,
+            "heritages",
+            &remove.heritages,
+        );
+        remove_named(&mut self.backgrounds, "backgrounds", &remove.backgrounds);
+        for name_trait in &remove.names {
+            if self.names.remove(&crate::Trait::new(name_trait)).is_none() {
+                warn!("Overlay removes unknown name table `{name_trait}`");
+            }
+        }
+        self.archetypes.retain(|x| {
+            !remove
+                .archetypes
+                .iter()
+                .any(|name| x.name() == Cow::Borrowed(name.as_str()))
+        });
+
+        let weights = &overlay.weights;
+        set_weights(&mut self.ancestries, "ancestries", &weights.ancestries);
+        set_weights(
+            &mut self.versatile_heritages,
+            "heritages",
+            &weights.heritages,
+        );
+        set_weights(&mut self.backgrounds, "backgrounds", &weights.backgrounds);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Background, Trait};
+
+    fn background(name: &str, trait_name: &str) -> Background {
+        Background {
+            name: name.into(),
+            traits: vec![Trait::new(trait_name)],
+            trainings: vec![],
+        }
+    }
+
+    #[test]
+    fn test_merge_and_overlay() {
+        let mut data = GeneratorData {
+            backgrounds: WeightMap::from_iter([
+                (background("Acolyte", "Core"), 1),
+                (background("Farmhand", "Core"), 1),
+            ]),
+            ..Default::default()
+        };
+        data.merge(GeneratorData {
+            backgrounds: WeightMap::from_iter([
+                (background("Acolyte", "Homebrew"), 2),
+                (background("Smuggler", "Homebrew"), 1),
+            ]),
+            ..Default::default()
+        });
+
+        assert_eq!(3, data.backgrounds.len());
+        let acolyte = data
+            .backgrounds
+            .iter()
+            .find(|x| x.0.name() == "Acolyte")
+            .unwrap();
+        assert_eq!(&[Trait::new("Homebrew")], acolyte.0.traits());
+        assert_eq!(2, *acolyte.1);
+
+        data.apply_overlay(&DataOverlay {
+            remove: OverlayRemovals {
+                backgrounds: vec!["Farmhand".into()],
+                ..Default::default()
+            },
+            weights: OverlayWeights {
+                backgrounds: HashMap::from([("Smuggler".into(), 5)]),
+                ..Default::default()
+            },
+        });
+
+        assert_eq!(2, data.backgrounds.len());
+        assert_eq!(
+            Some(&5),
+            data.backgrounds.get(&background("Smuggler", "Homebrew"))
+        );
+    }
+}
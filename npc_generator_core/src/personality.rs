@@ -0,0 +1,238 @@
+//! The personality of an NPC, what the habit line is made of.
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{AgeRange, Trait};
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// An entry of a personality table, written to follow "They", e.g.
+/// `"crack their knuckles when nervous"`.
+///
+/// The filters restrict the entry to NPCs matching one of their entries,
+/// empty filters match every NPC.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PersonalityTrait {
+    pub text: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default)]
+    pub backgrounds: Vec<String>,
+    #[serde(default)]
+    pub age_ranges: Vec<AgeRange>,
+    /// Traits of the ancestry or heritage.
+    #[serde(default)]
+    pub traits: Vec<Trait>,
+}
+
+impl PersonalityTrait {
+    pub fn applies_to(&self, npc: &PersonalityFilter) -> bool {
+        (self.backgrounds.is_empty() || self.backgrounds.iter().any(|x| x == npc.background))
+            && (self.age_ranges.is_empty() || self.age_ranges.contains(&npc.age_range))
+            && (self.traits.is_empty() || self.traits.iter().any(|x| npc.traits.contains(x)))
+    }
+}
+
+/// What the entries of the personality tables are filtered by.
+pub struct PersonalityFilter<'a> {
+    pub background: &'a str,
+    pub age_range: AgeRange,
+    pub traits: &'a [Trait],
+}
+
+/// The kinds of entries in the personality tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersonalityKind {
+    Mannerism,
+    SpeechQuirk,
+    Habit,
+    Like,
+    Dislike,
+}
+
+impl PersonalityKind {
+    pub fn values() -> &'static [PersonalityKind] {
+        static KINDS: [PersonalityKind; 5] = [
+            PersonalityKind::Mannerism,
+            PersonalityKind::SpeechQuirk,
+            PersonalityKind::Habit,
+            PersonalityKind::Like,
+            PersonalityKind::Dislike,
+        ];
+        &KINDS
+    }
+
+    /// The name of the kind in `personality.ron`, also the name of its sentence slot.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PersonalityKind::Mannerism => "mannerism",
+            PersonalityKind::SpeechQuirk => "speech_quirk",
+            PersonalityKind::Habit => "habit",
+            PersonalityKind::Like => "like",
+            PersonalityKind::Dislike => "dislike",
+        }
+    }
+
+    fn sentence(&self, text: &str) -> String {
+        match self {
+            PersonalityKind::Like => format!("They like {text}."),
+            PersonalityKind::Dislike => format!("They dislike {text}."),
+            _ => format!("They {text}."),
+        }
+    }
+}
+
+/// The `personality.ron` of a data pack.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Personality {
+    #[serde(default)]
+    pub mannerisms: Vec<PersonalityTrait>,
+    #[serde(default)]
+    pub speech_quirks: Vec<PersonalityTrait>,
+    #[serde(default)]
+    pub habits: Vec<PersonalityTrait>,
+    #[serde(default)]
+    pub likes: Vec<PersonalityTrait>,
+    #[serde(default)]
+    pub dislikes: Vec<PersonalityTrait>,
+}
+
+impl Personality {
+    pub fn table(&self, kind: PersonalityKind) -> &[PersonalityTrait] {
+        match kind {
+            PersonalityKind::Mannerism => &self.mannerisms,
+            PersonalityKind::SpeechQuirk => &self.speech_quirks,
+            PersonalityKind::Habit => &self.habits,
+            PersonalityKind::Like => &self.likes,
+            PersonalityKind::Dislike => &self.dislikes,
+        }
+    }
+
+    pub fn table_mut(&mut self, kind: PersonalityKind) -> &mut Vec<PersonalityTrait> {
+        match kind {
+            PersonalityKind::Mannerism => &mut self.mannerisms,
+            PersonalityKind::SpeechQuirk => &mut self.speech_quirks,
+            PersonalityKind::Habit => &mut self.habits,
+            PersonalityKind::Like => &mut self.likes,
+            PersonalityKind::Dislike => &mut self.dislikes,
+        }
+    }
+
+    /// Adds the entries of `layer`, replacing entries with the same text.
+    pub fn merge(&mut self, layer: Personality) {
+        for kind in PersonalityKind::values() {
+            let table = self.table_mut(*kind);
+            for entry in layer.table(*kind) {
+                table.retain(|x| x.text != entry.text);
+                table.push(entry.clone());
+            }
+        }
+    }
+
+    /// Rolls one entry of every table with entries applying to the NPC.
+    pub fn roll<'a>(
+        &'a self,
+        rng: &mut impl Rng,
+        npc: &PersonalityFilter,
+    ) -> Vec<(PersonalityKind, &'a str)> {
+        PersonalityKind::values()
+            .iter()
+            .filter_map(|kind| {
+                let entries = self
+                    .table(*kind)
+                    .iter()
+                    .filter(|x| x.applies_to(npc))
+                    .collect::<Vec<_>>();
+                let distribution = WeightedIndex::new(entries.iter().map(|x| x.weight)).ok()?;
+                Some((*kind, entries[distribution.sample(rng)].text.as_str()))
+            })
+            .collect()
+    }
+}
+
+/// The habit line from one or two of the rolled entries.
+pub fn personality_line(rng: &mut impl Rng, rolled: &[(PersonalityKind, &str)]) -> String {
+    let count = rng.gen_range(1..=2);
+    let chosen = rolled
+        .choose_multiple(rng, count)
+        .map(|(kind, text)| kind.sentence(text))
+        .collect::<Vec<_>>();
+    if chosen.is_empty() {
+        "They have no remarkable habits.".to_string()
+    } else {
+        chosen.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_roll_filters() {
+        let personality: Personality = ron::from_str(
+            r#"#![enable(unwrap_newtypes)]
+            (
+                habits: [
+                    (text: "sharpen their tools every evening", backgrounds: ["Blacksmith"]),
+                    (text: "nap in the afternoon", age_ranges: [Old, Venerable]),
+                ],
+                likes: [(text: "mushroom ale", traits: ["Dwarf"])],
+            )"#,
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let traits = [Trait::new("Dwarf")];
+        let npc = PersonalityFilter {
+            background: "Blacksmith",
+            age_range: AgeRange::Adult,
+            traits: &traits,
+        };
+
+        assert_eq!(
+            vec![
+                (PersonalityKind::Habit, "sharpen their tools every evening"),
+                (PersonalityKind::Like, "mushroom ale"),
+            ],
+            personality.roll(&mut rng, &npc)
+        );
+        let npc = PersonalityFilter {
+            background: "Farmhand",
+            traits: &[],
+            ..npc
+        };
+        assert!(personality.roll(&mut rng, &npc).is_empty());
+        assert_eq!(
+            "They have no remarkable habits.",
+            personality_line(&mut rng, &[])
+        );
+        let line = personality_line(&mut rng, &[(PersonalityKind::Like, "mushroom ale")]);
+        assert_eq!("They like mushroom ale.", line);
+    }
+
+    #[test]
+    fn test_merge_replaces_same_text() {
+        let entry = |text: &str, weight| PersonalityTrait {
+            text: text.into(),
+            weight,
+            backgrounds: vec![],
+            age_ranges: vec![],
+            traits: vec![],
+        };
+        let mut personality = Personality {
+            likes: vec![entry("cats", 1), entry("rain", 1)],
+            ..Default::default()
+        };
+        personality.merge(Personality {
+            likes: vec![entry("cats", 5)],
+            dislikes: vec![entry("rain", 1)],
+            ..Default::default()
+        });
+        assert_eq!(vec![entry("rain", 1), entry("cats", 5)], personality.likes);
+        assert_eq!(vec![entry("rain", 1)], personality.dislikes);
+    }
+}
@@ -0,0 +1,539 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{AbilityStats, NpcFlavor, PF2eStats, Skill, Statblock, Trait};
+
+const START_CODEBLOCK: &str = "```pf2e-stats";
+const END_CODEBLOCK: &str = "```";
+const SECTION_SEPARATOR: &str = "---";
+/// The trait every written statblock starts with.
+const UNIQUE_TRAIT: &str = "==Unique==";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Pf2eStatsParseError {
+    #[error("no ```pf2e-stats code block found")]
+    MissingCodeblock,
+    #[error("unexpected end of the pf2e-stats block, expected {0}")]
+    UnexpectedEnd(&'static str),
+    #[error("line {line}: expected {expected}, found `{found}`")]
+    UnexpectedLine {
+        line: usize,
+        expected: &'static str,
+        found: String,
+    },
+    #[error("line {line}: invalid flavor section with {paragraphs} paragraphs")]
+    InvalidFlavor { line: usize, paragraphs: usize },
+}
+
+struct Pf2eStatsParser<'a> {
+    lines: Vec<&'a str>,
+    position: usize,
+    /// Index of the line the values given to `parse_number` are from
+    value_line: usize,
+    first_line: usize,
+}
+
+impl<'a> Pf2eStatsParser<'a> {
+    fn unexpected(&self, expected: &'static str) -> Pf2eStatsParseError {
+        match self.lines.get(self.position) {
+            Some(found) => Pf2eStatsParseError::UnexpectedLine {
+                line: self.first_line + self.position + 1,
+                expected,
+                found: found.to_string(),
+            },
+            None => Pf2eStatsParseError::UnexpectedEnd(expected),
+        }
+    }
+
+    /// An error for the line the last value was read from.
+    fn unexpected_value(&self, expected: &'static str) -> Pf2eStatsParseError {
+        Pf2eStatsParseError::UnexpectedLine {
+            line: self.first_line + self.value_line + 1,
+            expected,
+            found: self.lines[self.value_line].to_string(),
+        }
+    }
+
+    fn skip_blank_lines(&mut self) {
+        while self
+            .lines
+            .get(self.position)
+            .is_some_and(|x| x.trim().is_empty())
+        {
+            self.position += 1;
+        }
+    }
+
+    /// Returns the rest of the next non-blank line after `prefix`.
+    fn expect_prefixed(
+        &mut self,
+        prefix: &str,
+        expected: &'static str,
+    ) -> Result<&'a str, Pf2eStatsParseError> {
+        self.skip_blank_lines();
+        let line = self.lines.get(self.position).copied();
+        match line.and_then(|x| x.strip_prefix(prefix)) {
+            Some(rest) => {
+                self.value_line = self.position;
+                self.position += 1;
+                Ok(rest)
+            }
+            None => Err(self.unexpected(expected)),
+        }
+    }
+
+    fn parse_number<T: FromStr>(
+        &self,
+        value: &str,
+        expected: &'static str,
+    ) -> Result<T, Pf2eStatsParseError> {
+        value.trim().trim_start_matches('+').parse().map_err(|_| {
+            Pf2eStatsParseError::UnexpectedLine {
+                line: self.first_line + self.value_line + 1,
+                expected,
+                found: value.to_string(),
+            }
+        })
+    }
+
+    fn parse_header(&mut self, statblock: &mut Statblock) -> Result<(), Pf2eStatsParseError> {
+        statblock.set_name(self.expect_prefixed("# ", "creature name")?);
+
+        let class_and_level = self.expect_prefixed("## ", "class and level")?;
+        let (class, level) = class_and_level
+            .rsplit_once(' ')
+            .unwrap_or(("", class_and_level));
+        statblock.set_class(class);
+        statblock.set_level(self.parse_number(level, "level")?);
+
+        self.skip_blank_lines();
+        let traits_line = self
+            .lines
+            .get(self.position)
+            .copied()
+            .filter(|x| x.starts_with("=="))
+            .ok_or_else(|| self.unexpected("traits"))?
+            .trim_end();
+        let traits = traits_line
+            .strip_prefix(UNIQUE_TRAIT)
+            .map_or(traits_line, str::trim_start);
+        statblock.set_traits(if traits.is_empty() {
+            Vec::new()
+        } else {
+            traits
+                .strip_prefix("==")
+                .and_then(|x| x.strip_suffix("=="))
+                .ok_or_else(|| self.unexpected("traits"))?
+                .split("== ==")
+                .map(Trait::new)
+                .collect()
+        });
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse_statistics(&mut self, statblock: &mut Statblock) -> Result<(), Pf2eStatsParseError> {
+        let perception = self.expect_prefixed("**Perception** ", "perception")?;
+        statblock.set_perception(self.parse_number(perception, "perception")?);
+
+        self.expect_prefixed("**Languages**", "languages")?;
+
+        let skills = self.expect_prefixed("**Skills**", "skills")?;
+        let mut parsed_skills = Vec::new();
+        for skill in skills.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let (name, value) = skill
+                .rsplit_once(' ')
+                .ok_or_else(|| self.unexpected_value("skill and modifier"))?;
+            let skill = Skill::from_str(name).map_err(|_| self.unexpected_value("skill name"))?;
+            parsed_skills.push((skill, self.parse_number(value, "skill modifier")?));
+        }
+        statblock.set_skills(parsed_skills);
+
+        self.skip_blank_lines();
+        let attributes_line = self
+            .lines
+            .get(self.position)
+            .copied()
+            .ok_or_else(|| self.unexpected("attributes"))?;
+        self.value_line = self.position;
+        let mut attributes = AbilityStats::default();
+        let mut found = 0;
+        for attribute in attributes_line.split(',').map(str::trim) {
+            let Some((name, value)) = attribute.rsplit_once(' ') else {
+                return Err(self.unexpected("attributes"));
+            };
+            let value: i8 = self.parse_number(value, "attribute modifier")?;
+            match name {
+                "**Str**" => attributes.strength = value,
+                "**Dex**" => attributes.dexterity = value,
+                "**Con**" => attributes.constitution = value,
+                "**Int**" => attributes.intelligence = value,
+                "**Wis**" => attributes.wisdom = value,
+                "**Cha**" => attributes.charisma = value,
+                _ => return Err(self.unexpected("attributes")),
+            }
+            found += 1;
+        }
+        if found != 6 {
+            return Err(self.unexpected("all six attributes"));
+        }
+        self.position += 1;
+        statblock.set_attributes(attributes);
+        Ok(())
+    }
+
+    fn parse_defenses(&mut self, statblock: &mut Statblock) -> Result<(), Pf2eStatsParseError> {
+        self.expect_prefixed(SECTION_SEPARATOR, "section separator")?;
+
+        let ac_and_saves = self.expect_prefixed("**AC** ", "armor class and saves")?;
+        let (armor_class, saves) = ac_and_saves
+            .split_once(';')
+            .ok_or_else(|| self.unexpected_value("armor class and saves"))?;
+        statblock.set_armor_class(self.parse_number(armor_class, "armor class")?);
+        for save in saves.split(',').map(str::trim) {
+            let Some((name, value)) = save.rsplit_once(' ') else {
+                return Err(self.unexpected_value("saves"));
+            };
+            let value: i16 = self.parse_number(value, "save modifier")?;
+            match name {
+                "**Fort**" => statblock.set_fortitude_save(value),
+                "**Ref**" => statblock.set_reflex_save(value),
+                "**Will**" => statblock.set_will_save(value),
+                _ => return Err(self.unexpected_value("saves")),
+            }
+        }
+
+        let hit_points = self.expect_prefixed("**HP** ", "hit points")?;
+        statblock.set_hit_points(self.parse_number(hit_points, "hit points")?);
+
+        self.expect_prefixed(SECTION_SEPARATOR, "section separator")?;
+
+        let speed = self.expect_prefixed("**Speed** ", "speed")?;
+        statblock.set_land_speed(self.parse_number(speed, "speed")?);
+
+        self.expect_prefixed(SECTION_SEPARATOR, "section separator")?;
+        Ok(())
+    }
+
+    /// The flavor section consists of six paragraphs, or seven when the
+    /// NPC has a lineage line.
+    ///
+    /// Every line is followed by a blank line, so an empty line shows up as two
+    /// more blank lines between the paragraphs. The section starts with one blank
+    /// line and ends with two. If counting them doesn't give six or seven lines,
+    /// e.g. in a note edited by hand, only the paragraphs are used.
+    fn parse_flavor(&mut self, statblock: &mut Statblock) -> Result<(), Pf2eStatsParseError> {
+        let start = self.position;
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut with_empty_lines: Vec<String> = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        let mut blank_lines: usize = 0;
+        for line in &self.lines[self.position..] {
+            if line.trim().is_empty() {
+                if !current.is_empty() {
+                    paragraphs.push(current.join("\n"));
+                    with_empty_lines.push(current.join("\n"));
+                    current.clear();
+                }
+                blank_lines += 1;
+            } else {
+                if current.is_empty() {
+                    // One blank line separates the paragraphs
+                    let empty_lines = blank_lines.saturating_sub(1) / 2;
+                    with_empty_lines.extend((0..empty_lines).map(|_| String::new()));
+                    blank_lines = 0;
+                }
+                current.push(line);
+            }
+        }
+        if !current.is_empty() {
+            paragraphs.push(current.join("\n"));
+            with_empty_lines.push(current.join("\n"));
+        } else if !paragraphs.is_empty() {
+            let empty_lines = blank_lines.saturating_sub(2) / 2;
+            with_empty_lines.extend((0..empty_lines).map(|_| String::new()));
+        }
+        self.position = self.lines.len();
+
+        if matches!(with_empty_lines.len(), 6 | 7) {
+            paragraphs = with_empty_lines;
+        }
+        let paragraph_count = paragraphs.len();
+        let mut paragraphs = paragraphs.into_iter();
+        let mut next = || paragraphs.next().unwrap_or_default();
+        let flavor = match paragraph_count {
+            0 => NpcFlavor::default(),
+            6 => NpcFlavor {
+                description_line: next(),
+                hair_and_eyes_line: next(),
+                skin_line: next(),
+                lineage_line: None,
+                size_and_build_line: next(),
+                face_line: next(),
+                habit_line: next(),
+            },
+            7 => NpcFlavor {
+                description_line: next(),
+                hair_and_eyes_line: next(),
+                skin_line: next(),
+                lineage_line: Some(next()),
+                size_and_build_line: next(),
+                face_line: next(),
+                habit_line: next(),
+            },
+            count => {
+                return Err(Pf2eStatsParseError::InvalidFlavor {
+                    line: self.first_line + start + 1,
+                    paragraphs: count,
+                })
+            }
+        };
+        statblock.set_flavor(flavor);
+        Ok(())
+    }
+
+    fn parse(mut self) -> Result<Statblock, Pf2eStatsParseError> {
+        let mut statblock = Statblock::default();
+        self.parse_header(&mut statblock)?;
+        self.parse_statistics(&mut statblock)?;
+        self.parse_defenses(&mut statblock)?;
+        self.parse_flavor(&mut statblock)?;
+        Ok(statblock)
+    }
+}
+
+impl PF2eStats {
+    /// Parses every ```` ```pf2e-stats ```` block in the given text, for
+    /// example a whole note with several NPCs.
+    pub fn parse_all(text: &str) -> Result<Vec<PF2eStats>, Pf2eStatsParseError> {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut results = Vec::new();
+        let mut position = 0;
+        while let Some(start) = lines[position..]
+            .iter()
+            .position(|x| x.trim_end() == START_CODEBLOCK)
+            .map(|x| x + position + 1)
+        {
+            let end = lines[start..]
+                .iter()
+                .position(|x| x.trim_end() == END_CODEBLOCK)
+                .map(|x| x + start)
+                .ok_or(Pf2eStatsParseError::UnexpectedEnd("end of code block"))?;
+            let parser = Pf2eStatsParser {
+                lines: lines[start..end].to_vec(),
+                position: 0,
+                value_line: 0,
+                first_line: start,
+            };
+            results.push(parser.parse()?.into_pf2e_stats());
+            position = end + 1;
+        }
+
+        if results.is_empty() {
+            Err(Pf2eStatsParseError::MissingCodeblock)
+        } else {
+            Ok(results)
+        }
+    }
+}
+
+impl FromStr for PF2eStats {
+    type Err = Pf2eStatsParseError;
+
+    /// Parses the first ```` ```pf2e-stats ```` block in the given text.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_all(s)?
+            .into_iter()
+            .next()
+            .ok_or(Pf2eStatsParseError::MissingCodeblock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_statblock() -> Statblock {
+        let mut statblock = Statblock::default();
+        statblock.set_name("Mirela Voss");
+        statblock.set_class("Martial Disciple");
+        statblock.set_level(-1);
+        statblock.set_traits(vec![
+            Trait::new("Human"),
+            Trait::new("Humanoid"),
+            Trait::new("Medium"),
+        ]);
+        statblock.set_perception(4);
+        statblock.set_skills(vec![
+            (Skill::Acrobatics, 5),
+            (Skill::Lore("Sailing".into()), 3),
+        ]);
+        statblock.set_attributes(AbilityStats {
+            strength: 1,
+            dexterity: 3,
+            constitution: 0,
+            intelligence: -1,
+            wisdom: 2,
+            charisma: 1,
+        });
+        statblock.set_armor_class(17);
+        statblock.set_fortitude_save(3);
+        statblock.set_reflex_save(7);
+        statblock.set_will_save(-2);
+        statblock.set_hit_points(12);
+        statblock.set_land_speed(25);
+        statblock
+    }
+
+    #[test]
+    fn test_round_trip_without_flavor() {
+        let written = example_statblock().as_pf2e_stats().to_string();
+        let parsed: PF2eStats = written.parse().unwrap();
+        assert_eq!(written, parsed.to_string());
+    }
+
+    #[test]
+    fn test_round_trip_with_lineage() {
+        let mut statblock = example_statblock();
+        statblock.set_flavor(NpcFlavor {
+            description_line: "Mirela is an adult human.".into(),
+            hair_and_eyes_line: "They have heterochromatic eyes.\nTheir left eye is blue.".into(),
+            skin_line: "They have smooth skin.".into(),
+            lineage_line: Some("They are of the Brine May lineage.".into()),
+            size_and_build_line: "They are slight.".into(),
+            face_line: "They have a face.".into(),
+            habit_line: "They have habits.".into(),
+        });
+        let written = statblock.as_pf2e_stats().to_string();
+        let parsed: PF2eStats = written.parse().unwrap();
+        assert_eq!(written, parsed.to_string());
+
+        // Blank lines added by hand are ignored when every line has text
+        let edited = written.replace("They are slight.\n", "\n\nThey are slight.\n");
+        assert_eq!(written, edited.parse::<PF2eStats>().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_round_trip_with_empty_line() {
+        let mut statblock = example_statblock();
+        statblock.set_flavor(NpcFlavor {
+            description_line: "Mirela is an adult human.".into(),
+            hair_and_eyes_line: "They have brown eyes.".into(),
+            skin_line: String::new(),
+            lineage_line: None,
+            size_and_build_line: "They are slight.".into(),
+            face_line: String::new(),
+            habit_line: "They have habits.".into(),
+        });
+        let written = statblock.as_pf2e_stats().to_string();
+        let parsed: Statblock = written.parse::<PF2eStats>().unwrap().into();
+        assert_eq!("", parsed.flavor().skin_line);
+        assert_eq!("They are slight.", parsed.flavor().size_and_build_line);
+        assert_eq!("", parsed.flavor().face_line);
+        assert_eq!("They have habits.", parsed.flavor().habit_line);
+        assert_eq!(written, parsed.as_pf2e_stats().to_string());
+    }
+
+    #[test]
+    fn test_round_trip_with_empty_first_and_last_lines() {
+        let mut statblock = example_statblock();
+        statblock.set_flavor(NpcFlavor {
+            description_line: String::new(),
+            hair_and_eyes_line: "They have brown eyes.".into(),
+            skin_line: "They have smooth skin.".into(),
+            lineage_line: Some(String::new()),
+            size_and_build_line: "They are slight.".into(),
+            face_line: "They have a face.".into(),
+            habit_line: String::new(),
+        });
+        let written = statblock.as_pf2e_stats().to_string();
+        let parsed: Statblock = written.parse::<PF2eStats>().unwrap().into();
+        assert_eq!("", parsed.flavor().description_line);
+        assert_eq!(Some(""), parsed.flavor().lineage_line.as_deref());
+        assert_eq!("", parsed.flavor().habit_line);
+        assert_eq!(written, parsed.as_pf2e_stats().to_string());
+    }
+
+    #[test]
+    fn test_keeps_unique_trait() {
+        let mut statblock = example_statblock();
+        statblock.set_traits(vec![Trait::new("Human"), Trait::new("Unique")]);
+        let written = statblock.as_pf2e_stats().to_string();
+        let parsed: Statblock = written.parse::<PF2eStats>().unwrap().into();
+        assert_eq!(
+            vec![Trait::new("Human"), Trait::new("Unique")],
+            parsed.traits().to_vec()
+        );
+        statblock.set_traits(vec![]);
+        let written = statblock.as_pf2e_stats().to_string();
+        let parsed: Statblock = written.parse::<PF2eStats>().unwrap().into();
+        assert!(parsed.traits().is_empty());
+    }
+
+    #[test]
+    fn test_error_lines() {
+        let written = format!("Notes\n{}", example_statblock().as_pf2e_stats());
+        let line_of =
+            |prefix: &str| written.lines().position(|x| x.starts_with(prefix)).unwrap() + 1;
+        let error = written
+            .replace("**HP** 12", "**HP** many")
+            .parse::<PF2eStats>();
+        assert_eq!(
+            Err(Pf2eStatsParseError::UnexpectedLine {
+                line: line_of("**HP**"),
+                expected: "hit points",
+                found: "many".into(),
+            }),
+            error.map(|_| ())
+        );
+        let error = written
+            .replace("**Str** +1", "**Str** x")
+            .parse::<PF2eStats>();
+        assert_eq!(
+            Err(Pf2eStatsParseError::UnexpectedLine {
+                line: line_of("**Str**"),
+                expected: "attribute modifier",
+                found: "x".into(),
+            }),
+            error.map(|_| ())
+        );
+        let skills = written
+            .lines()
+            .find(|x| x.starts_with("**Skills**"))
+            .unwrap();
+        let error = written
+            .replace(skills, "**Skills** Acrobatics")
+            .parse::<PF2eStats>();
+        assert_eq!(
+            Err(Pf2eStatsParseError::UnexpectedLine {
+                line: line_of("**Skills**"),
+                expected: "skill and modifier",
+                found: "**Skills** Acrobatics".into(),
+            }),
+            error.map(|_| ())
+        );
+        let saves = written.lines().find(|x| x.starts_with("**AC**")).unwrap();
+        let bad_saves = saves.replace("**Fort**", "**Fortitude**");
+        let error = written.replace(saves, &bad_saves).parse::<PF2eStats>();
+        assert_eq!(
+            Err(Pf2eStatsParseError::UnexpectedLine {
+                line: line_of("**AC**"),
+                expected: "saves",
+                found: bad_saves,
+            }),
+            error.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_parse_all_in_note() {
+        let block = example_statblock().as_pf2e_stats().to_string();
+        let note = format!("Some notes\n\n{block}\nMore notes\n{block}");
+        assert_eq!(2, PF2eStats::parse_all(&note).unwrap().len());
+        assert_eq!(
+            Err(Pf2eStatsParseError::MissingCodeblock),
+            "no statblock".parse::<PF2eStats>().map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Limits for running data pack scripts, which may come from untrusted packs.
+
+use std::{
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use thiserror::Error;
+
+/// Limits applied to every compilation and call of a data pack script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptLimits {
+    /// Function calls a script may make. Loops are recursive calls in gluon,
+    /// so this bounds every loop.
+    pub max_steps: u64,
+    /// Bytes a call may allocate
+    pub max_memory: usize,
+    /// Values on the VM stack, bounds the recursion depth
+    pub max_stack_size: u32,
+    pub timeout: Duration,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_steps: 1_000_000,
+            max_memory: 16 * 1024 * 1024,
+            max_stack_size: 100_000,
+            timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+lazy_static! {
+    static ref SCRIPT_LIMITS: RwLock<ScriptLimits> = RwLock::new(ScriptLimits::default());
+}
+
+/// The limits used for script calls from now on, on every thread.
+pub fn set_script_limits(limits: ScriptLimits) {
+    *SCRIPT_LIMITS.write().unwrap() = limits;
+}
+
+pub fn script_limits() -> ScriptLimits {
+    *SCRIPT_LIMITS.read().unwrap()
+}
+
+/// Modules scripts may import. The others can reach the file system,
+/// processes or other threads.
+pub const ALLOWED_IMPORTS: &[&str] = &[
+    "npc_generator.core",
+    "npc_generator.random",
+    "std.applicative",
+    "std.array",
+    "std.bool",
+    "std.byte",
+    "std.char",
+    "std.cmp",
+    "std.float",
+    "std.foldable",
+    "std.function",
+    "std.functor",
+    "std.int",
+    "std.list",
+    "std.map",
+    "std.monad",
+    "std.monoid",
+    "std.option",
+    "std.prelude",
+    "std.result",
+    "std.semigroup",
+    "std.show",
+    "std.string",
+    "std.traversable",
+    "std.types",
+];
+
+/// A script broke one of the [`ScriptLimits`] or the import allow list.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SandboxViolation {
+    #[error("exceeded the budget of {0} steps")]
+    StepLimit(u64),
+    #[error("exceeded the memory limit of {0} bytes")]
+    MemoryLimit(usize),
+    #[error("exceeded the stack size limit of {0} values")]
+    StackLimit(u32),
+    #[error("didn't finish within {0:?}")]
+    Timeout(Duration),
+    #[error("imports `{0}`, which scripts aren't allowed to use")]
+    ForbiddenImport(String),
+}
+
+/// Checks every `import!` of the script against [`ALLOWED_IMPORTS`].
+///
+/// File imports like `import! "npc_generator/core.glu"` are checked as the
+/// module they name.
+pub fn check_imports(script: &str) -> Result<(), SandboxViolation> {
+    const IMPORT_MACRO: &str = "import!";
+    for (index, _) in script.match_indices(IMPORT_MACRO) {
+        let rest = script[index + IMPORT_MACRO.len()..].trim_start_matches([' ', '\t', '(']);
+        let module = match rest.strip_prefix('"') {
+            Some(path) => {
+                let path = &path[..path.find('"').unwrap_or(path.len())];
+                path.strip_suffix(".glu").unwrap_or(path).replace('/', ".")
+            }
+            None => rest
+                .chars()
+                .take_while(|x| x.is_alphanumeric() || *x == '_' || *x == '.')
+                .collect(),
+        };
+        if !ALLOWED_IMPORTS.contains(&module.as_str()) {
+            return Err(SandboxViolation::ForbiddenImport(module));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    limits: ScriptLimits,
+    steps: u64,
+    started: Instant,
+    violation: Option<SandboxViolation>,
+}
+
+/// Bookkeeping of the running script call, shared with the hook of the VM.
+#[derive(Debug)]
+pub(crate) struct Budget(Mutex<BudgetState>);
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self(Mutex::new(BudgetState {
+            limits: ScriptLimits::default(),
+            steps: 0,
+            started: Instant::now(),
+            violation: None,
+        }))
+    }
+}
+
+impl Budget {
+    /// Resets the budget for a new call.
+    pub(crate) fn start(&self, limits: ScriptLimits) {
+        *self.0.lock().unwrap() = BudgetState {
+            limits,
+            steps: 0,
+            started: Instant::now(),
+            violation: None,
+        };
+    }
+
+    /// Accounts for one step, failing once a limit is exceeded.
+    pub(crate) fn step(&self) -> Result<(), SandboxViolation> {
+        let mut state = self.0.lock().unwrap();
+        state.steps += 1;
+        let violation = if state.steps > state.limits.max_steps {
+            SandboxViolation::StepLimit(state.limits.max_steps)
+        } else if state.started.elapsed() > state.limits.timeout {
+            SandboxViolation::Timeout(state.limits.timeout)
+        } else {
+            return Ok(());
+        };
+        state.violation = Some(violation.clone());
+        Err(violation)
+    }
+
+    /// The violation that stopped the last call, if any.
+    pub(crate) fn take_violation(&self) -> Option<SandboxViolation> {
+        self.0.lock().unwrap().violation.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_imports() {
+        let script = r#"
+            let { AgeRange } = import! "npc_generator/core.glu"
+            let string = import! std.string
+            \x -> x"#;
+        assert_eq!(Ok(()), check_imports(script));
+        assert_eq!(
+            Err(SandboxViolation::ForbiddenImport("std.io".into())),
+            check_imports("let io = import! std.io\n()")
+        );
+        assert_eq!(
+            Err(SandboxViolation::ForbiddenImport("secrets".into())),
+            check_imports(r#"import!("secrets.glu")"#)
+        );
+    }
+
+    #[test]
+    fn test_budget_step_limit() {
+        let budget = Budget::default();
+        budget.start(ScriptLimits {
+            max_steps: 2,
+            ..Default::default()
+        });
+        assert_eq!(Ok(()), budget.step());
+        assert_eq!(Ok(()), budget.step());
+        assert_eq!(Err(SandboxViolation::StepLimit(2)), budget.step());
+        assert_eq!(
+            Some(SandboxViolation::StepLimit(2)),
+            budget.take_violation()
+        );
+
+        budget.start(ScriptLimits {
+            timeout: Duration::ZERO,
+            ..Default::default()
+        });
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(
+            Err(SandboxViolation::Timeout(Duration::ZERO)),
+            budget.step()
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Randomness for data pack scripts, the gluon module `npc_generator.random`.
+//!
+//! Every script call gets its own generator, seeded from the random number
+//! generator of the step calling the script, so NPCs generated from the same
+//! seed get the same text.
+
+use std::cell::RefCell;
+
+use gluon::{
+    import::add_extern_module,
+    primitive, record,
+    vm::{self, api::RuntimeResult, types::VmInt, ExternModule},
+    Thread, ThreadExt,
+};
+use rand::{distributions::WeightedIndex, rngs::StdRng, Rng, SeedableRng};
+
+use crate::{DiceExpression, DiceTerm, MAX_DICE, MAX_SIDES};
+
+pub(crate) const RANDOM_MODULE: &str = "npc_generator.random";
+const RANDOM_PRIM_MODULE: &str = "npc_generator.random.prim";
+
+const RANDOM_MODULE_SOURCE: &str = r#"
+let prim = import! npc_generator.random.prim
+let array = import! std.array
+let { Option } = import! std.types
+
+/// A value of the list given to `choose_weighted`
+type Weighted a = { value : a, weight : Float }
+
+/// One of the values, or `None` if the array is empty
+let choose xs : Array a -> Option a =
+    if array.is_empty xs then None
+    else Some (array.index xs (prim.int 0 (array.len xs - 1)))
+
+/// One of the values, picked with a chance proportional to its weight.
+/// `None` if no value has a positive weight.
+let choose_weighted xs : Array (Weighted a) -> Option a =
+    let index = prim.weighted_index (array.functor.map (\x -> x.weight) xs)
+    if index < 0 then None
+    else
+        let chosen = array.index xs index
+        Some chosen.value
+
+{
+    Weighted,
+    int = prim.int,
+    float = prim.float,
+    roll = prim.roll,
+    roll_dice = prim.roll_dice,
+    choose,
+    choose_weighted,
+}
+"#;
+
+thread_local! {
+    static SCRIPT_RNG: RefCell<StdRng> = RefCell::new(StdRng::seed_from_u64(0));
+}
+
+/// Seeds the generator of the next script call on this thread.
+pub(crate) fn seed_script_rng(seed: u64) {
+    SCRIPT_RNG.with(|x| *x.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    SCRIPT_RNG.with(|x| f(&mut x.borrow_mut()))
+}
+
+/// A number between `low` and `high`, both included. `low` if `high` is smaller.
+fn int(low: VmInt, high: VmInt) -> VmInt {
+    if high <= low {
+        low
+    } else {
+        with_rng(|rng| rng.gen_range(low..=high))
+    }
+}
+
+/// A number from 0 up to 1, excluding 1.
+fn float(_: ()) -> f64 {
+    with_rng(|rng| rng.gen())
+}
+
+/// The index of one of the weights, -1 if none of them is positive.
+fn weighted_index(weights: Vec<f64>) -> VmInt {
+    match WeightedIndex::new(weights.iter().map(|x| x.max(0.0))) {
+        Ok(distribution) => with_rng(|rng| rng.sample(distribution) as VmInt),
+        Err(_) => -1,
+    }
+}
+
+/// The sum of rolling `count` dice with `sides` sides.
+fn roll(count: VmInt, sides: VmInt) -> RuntimeResult<VmInt, String> {
+    let count = match u32::try_from(count) {
+        Ok(x) if (1..=MAX_DICE).contains(&x) => x,
+        _ => {
+            return RuntimeResult::Panic(format!(
+                "Can't roll {count} dice, the count must be between 1 and {MAX_DICE}"
+            ))
+        }
+    };
+    let sides = match u32::try_from(sides) {
+        Ok(x) if (1..=MAX_SIDES).contains(&x) => x,
+        _ => {
+            return RuntimeResult::Panic(format!(
+                "Can't roll dice with {sides} sides, the sides must be between 1 and {MAX_SIDES}"
+            ))
+        }
+    };
+    let dice = DiceTerm::Dice {
+        negative: false,
+        count,
+        sides,
+        keep_highest: None,
+    };
+    RuntimeResult::Return(with_rng(|rng| dice.roll(rng)))
+}
+
+/// The result of a dice expression like `2d6+3`, written as in the data files.
+fn roll_dice(expression: String) -> RuntimeResult<VmInt, String> {
+    match expression.parse::<DiceExpression>() {
+        Ok(dice) => RuntimeResult::Return(with_rng(|rng| dice.roll(rng))),
+        Err(err) => RuntimeResult::Panic(format!("Can't roll `{expression}`: {err}")),
+    }
+}
+
+fn load_prim(vm: &Thread) -> vm::Result<ExternModule> {
+    ExternModule::new(
+        vm,
+        record! {
+            int => primitive!(2, int),
+            float => primitive!(1, float),
+            weighted_index => primitive!(1, weighted_index),
+            roll => primitive!(2, roll),
+            roll_dice => primitive!(1, roll_dice)
+        },
+    )
+}
+
+/// Makes `npc_generator.random` importable on the VM.
+pub(crate) fn add_random_module(vm: &Thread) -> gluon::Result<()> {
+    add_extern_module(vm, RANDOM_PRIM_MODULE, load_prim);
+    vm.load_script(RANDOM_MODULE, RANDOM_MODULE_SOURCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_calls_repeat() {
+        let draw = || (int(1, 100), float(()), weighted_index(vec![1.0, 0.0, 3.0]));
+
+        seed_script_rng(42);
+        let first = [draw(), draw()];
+        seed_script_rng(42);
+        assert_eq!(first, [draw(), draw()]);
+
+        assert_eq!(5, int(5, 5));
+        assert_eq!(-1, weighted_index(vec![0.0, -1.0]));
+        assert_eq!(1, weighted_index(vec![0.0, 2.0]));
+        assert!(matches!(roll(2, 6), RuntimeResult::Return(2..=12)));
+        assert!(matches!(roll(1, 7), RuntimeResult::Return(1..=7)));
+        assert!(matches!(roll(1, 0), RuntimeResult::Panic(_)));
+        assert!(matches!(roll(-2, 6), RuntimeResult::Panic(_)));
+        assert!(matches!(roll(1001, 6), RuntimeResult::Panic(_)));
+        assert!(matches!(roll(1, 1 << 40), RuntimeResult::Panic(_)));
+        assert!(matches!(
+            roll_dice("2d6 + 3".into()),
+            RuntimeResult::Return(5..=15)
+        ));
+        assert!(matches!(
+            roll_dice("4d6kh3-1d4".into()),
+            RuntimeResult::Return(-1..=17)
+        ));
+        assert!(matches!(roll_dice("2d".into()), RuntimeResult::Panic(_)));
+    }
+}
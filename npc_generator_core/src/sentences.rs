@@ -0,0 +1,348 @@
+//! Weighted sentence templates for the flavor lines, the declarative
+//! alternative to gluon scripts.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    formats::{FlavorLine, FlavorStatblock},
+    AgeRange,
+};
+
+/// Name of the description line in `sentences.ron`.
+pub const DESCRIPTION_LINE: &str = "description_line";
+/// Name of the lineage line in `sentences.ron`, only used for heritages with a lineage.
+pub const LINEAGE_LINE: &str = "lineage_line";
+
+/// The slots every template can use. Templates for the lineage line can use
+/// `lineage` too. A slot written with a capital letter, like `{Pronoun.subject}`,
+/// starts with one.
+///
+/// `pronoun.s`, `pronoun.es`, `pronoun.is` and `pronoun.has` make verbs agree
+/// with the pronoun, e.g. `{Pronoun.subject} hum{pronoun.s}` gives "She hums"
+/// but "They hum".
+pub const SENTENCE_SLOTS: &[&str] = &[
+    "line",
+    "name",
+    "level",
+    "age",
+    "age_range",
+    "sex",
+    "ancestry",
+    "heritage",
+    "background",
+    "class",
+    "size",
+    "pronoun.subject",
+    "pronoun.object",
+    "pronoun.possessive",
+    "pronoun.reflexive",
+    "pronoun.s",
+    "pronoun.es",
+    "pronoun.is",
+    "pronoun.has",
+    "hair",
+    "hair_length",
+    "hair_type",
+    "hair_color",
+    "hair_substance",
+    "eyes",
+    "eye_color",
+    "skin",
+    "skin_texture",
+    "skin_tone",
+    "skin_substance",
+    "height",
+    "weight",
+    "build",
+    "face_shape",
+    "nose",
+    "ears",
+    "jaw",
+    "facial_hair",
+    "face_features",
+    "mannerism",
+    "speech_quirk",
+    "habit",
+    "like",
+    "dislike",
+    "lineage",
+];
+
+/// The lines of an [`NpcFlavor`](crate::NpcFlavor) templates can be written for.
+pub fn sentence_lines() -> impl Iterator<Item = &'static str> {
+    [DESCRIPTION_LINE, LINEAGE_LINE]
+        .into_iter()
+        .chain(FlavorLine::values().iter().map(|x| x.name()))
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// A variant of a flavor line with `{slot}` placeholders, see [`SENTENCE_SLOTS`].
+///
+/// The filters restrict the template to NPCs matching one of their entries,
+/// empty filters match every NPC.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SentenceTemplate {
+    pub text: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default)]
+    pub ancestries: Vec<String>,
+    #[serde(default)]
+    pub heritages: Vec<String>,
+    #[serde(default)]
+    pub age_ranges: Vec<AgeRange>,
+    #[serde(default)]
+    pub backgrounds: Vec<String>,
+}
+
+impl SentenceTemplate {
+    pub fn applies_to(&self, statblock: &FlavorStatblock) -> bool {
+        fn matches<T: PartialEq<U>, U: ?Sized>(filter: &[T], value: &U) -> bool {
+            filter.is_empty() || filter.iter().any(|x| x == value)
+        }
+        matches(&self.ancestries, statblock.ancestry.as_str())
+            && matches(&self.heritages, statblock.heritage.as_str())
+            && matches(&self.age_ranges, &statblock.age_range)
+            && matches(&self.backgrounds, statblock.background.as_str())
+    }
+
+    /// The names of the slots in the text, as written.
+    pub fn slots(&self) -> Vec<&str> {
+        let mut slots = Vec::new();
+        parse(&self.text, |part| {
+            if let Part::Slot(x) = part {
+                slots.push(x)
+            }
+        });
+        slots
+    }
+}
+
+/// The `sentences.ron` of a data pack, the templates by the name of their line.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct SentenceTemplates(pub HashMap<String, Vec<SentenceTemplate>>);
+
+impl SentenceTemplates {
+    /// Picks one of the templates of `line` applying to the NPC and fills it
+    /// in, `None` if none applies.
+    pub fn generate(
+        &self,
+        rng: &mut impl Rng,
+        line: &str,
+        statblock: &FlavorStatblock,
+        slots: &SentenceSlots,
+    ) -> Option<String> {
+        let templates = self
+            .0
+            .get(line)?
+            .iter()
+            .filter(|x| x.applies_to(statblock))
+            .collect::<Vec<_>>();
+        let distribution = WeightedIndex::new(templates.iter().map(|x| x.weight)).ok()?;
+        Some(slots.fill(&templates[distribution.sample(rng)].text))
+    }
+}
+
+/// The values of the slots of one NPC.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SentenceSlots(BTreeMap<&'static str, String>);
+
+impl SentenceSlots {
+    /// The slots taken from the statblock.
+    pub fn new(statblock: &FlavorStatblock) -> Self {
+        let (subject, object, possessive, reflexive) = match statblock.sex.as_str() {
+            "male" => ("he", "him", "his", "himself"),
+            "female" => ("she", "her", "her", "herself"),
+            _ => ("they", "them", "their", "themselves"),
+        };
+        let (s, es, is, has) = if subject == "they" {
+            ("", "", "are", "have")
+        } else {
+            ("s", "es", "is", "has")
+        };
+        Self(BTreeMap::from([
+            ("name", statblock.name.clone()),
+            ("level", statblock.level.to_string()),
+            ("age", statblock.age.to_string()),
+            ("age_range", statblock.age_range.to_string()),
+            ("sex", statblock.sex.clone()),
+            ("ancestry", statblock.ancestry.clone()),
+            ("heritage", statblock.heritage.clone()),
+            ("background", statblock.background.clone()),
+            ("class", statblock.class.clone()),
+            ("size", statblock.size.clone()),
+            ("pronoun.subject", subject.to_string()),
+            ("pronoun.object", object.to_string()),
+            ("pronoun.possessive", possessive.to_string()),
+            ("pronoun.reflexive", reflexive.to_string()),
+            ("pronoun.s", s.to_string()),
+            ("pronoun.es", es.to_string()),
+            ("pronoun.is", is.to_string()),
+            ("pronoun.has", has.to_string()),
+        ]))
+    }
+
+    pub fn get(&self, slot: &str) -> Option<&str> {
+        self.0.get(slot).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, slot: &'static str, value: impl Into<String>) {
+        self.0.insert(slot, value.into());
+    }
+
+    /// The slots with `line` set, the line generated without templates.
+    pub fn with_line(&self, line: impl Into<String>) -> Self {
+        let mut slots = self.clone();
+        slots.insert("line", line);
+        slots
+    }
+
+    /// Replaces the slots of `text` with their values. `{{` and `}}` stand for
+    /// braces, slots without a value are kept as they are.
+    pub fn fill(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        parse(text, |part| match part {
+            Part::Text(x) => result.push_str(x),
+            Part::Slot(slot) => {
+                let (name, capitalized) = slot_name(slot);
+                match self.get(&name) {
+                    Some(value) if capitalized => {
+                        let mut value = value.chars();
+                        result.extend(value.next().into_iter().flat_map(char::to_uppercase));
+                        result.extend(value);
+                    }
+                    Some(value) => result.push_str(value),
+                    None => {
+                        result.push('{');
+                        result.push_str(slot);
+                        result.push('}');
+                    }
+                }
+            }
+        });
+        result
+    }
+}
+
+/// The name of a slot as written in a template, and whether it is capitalized.
+pub fn slot_name(slot: &str) -> (String, bool) {
+    let mut chars = slot.chars();
+    let first = chars.next().unwrap_or_default();
+    (
+        first.to_lowercase().chain(chars).collect(),
+        first.is_uppercase(),
+    )
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Slot(&'a str),
+}
+
+fn parse<'a>(text: &'a str, mut part: impl FnMut(Part<'a>)) {
+    let mut rest = text;
+    while let Some(index) = rest.find(['{', '}']) {
+        part(Part::Text(&rest[..index]));
+        let brace = &rest[index..index + 1];
+        rest = &rest[index + 1..];
+        if let Some(x) = rest.strip_prefix(brace) {
+            part(Part::Text(brace));
+            rest = x;
+        } else if let (Some(end), "{") = (rest.find('}'), brace) {
+            part(Part::Slot(&rest[..end]));
+            rest = &rest[end + 1..];
+        } else {
+            part(Part::Text(brace));
+        }
+    }
+    part(Part::Text(rest));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_fill() {
+        let statblock = FlavorStatblock {
+            name: "Valeros".into(),
+            sex: "male".into(),
+            ..Default::default()
+        };
+        let slots = SentenceSlots::new(&statblock).with_line("He is tall.");
+        assert_eq!(
+            "He scratches his beard {sometimes}. He is tall. {x}",
+            slots.fill(
+                "{Pronoun.subject} scratches {pronoun.possessive} beard {{sometimes}}. {line} {x}"
+            )
+        );
+        let text = "{Pronoun.subject} hum{pronoun.s} and watch{pronoun.es}, {pronoun.subject} {pronoun.is} calm.";
+        assert_eq!("He hums and watches, he is calm.", slots.fill(text));
+        let leshy = SentenceSlots::new(&FlavorStatblock {
+            sex: "".into(),
+            ..Default::default()
+        });
+        assert_eq!("They hum and watch, they are calm.", leshy.fill(text));
+        assert_eq!(
+            "They have leaves.",
+            leshy.fill("{Pronoun.subject} {pronoun.has} leaves.")
+        );
+        assert_eq!(
+            vec!["Pronoun.subject", "x"],
+            SentenceTemplate {
+                text: "{Pronoun.subject} is {{not}} {x}".into(),
+                weight: 1,
+                ancestries: vec![],
+                heritages: vec![],
+                age_ranges: vec![],
+                backgrounds: vec![],
+            }
+            .slots()
+        );
+    }
+
+    #[test]
+    fn test_generate_filters() {
+        let templates: SentenceTemplates = ron::from_str(
+            r#"{
+                "face_line": [
+                    (text: "A dwarf face.", ancestries: ["Dwarf"]),
+                    (text: "An old face.", weight: 3, age_ranges: [Old, Venerable]),
+                ],
+            }"#,
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let statblock = FlavorStatblock {
+            ancestry: "Dwarf".into(),
+            age_range: AgeRange::Adult,
+            ..Default::default()
+        };
+        let slots = SentenceSlots::new(&statblock);
+
+        assert_eq!(
+            Some("A dwarf face.".to_string()),
+            templates.generate(&mut rng, "face_line", &statblock, &slots)
+        );
+        let statblock = FlavorStatblock {
+            ancestry: "Elf".into(),
+            ..statblock
+        };
+        assert_eq!(
+            None,
+            templates.generate(&mut rng, "face_line", &statblock, &slots)
+        );
+        assert_eq!(
+            None,
+            templates.generate(&mut rng, "habit_line", &statblock, &slots)
+        );
+    }
+}
@@ -9,7 +9,7 @@ pub enum Size {
     Medium,
     Large,
     Huge,
-    Garganutan,
+    Gargantuan,
 }
 
 impl Display for Size {
@@ -23,7 +23,7 @@ impl Display for Size {
                 Self::Medium => "Medium",
                 Self::Large => "Large",
                 Self::Huge => "Huge",
-                Self::Garganutan => "Gargantuan",
+                Self::Gargantuan => "Gargantuan",
             }
         )
     }
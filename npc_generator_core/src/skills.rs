@@ -1,6 +1,7 @@
-use std::{fmt::Display, sync::Arc};
+use std::{fmt::Display, str::FromStr, sync::Arc};
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Serialize, Deserialize, Hash)]
 pub enum Skill {
@@ -74,3 +75,22 @@ impl Display for Skill {
         )
     }
 }
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("unknown skill `{0}`")]
+pub struct UnknownSkillError(pub String);
+
+impl FromStr for Skill {
+    type Err = UnknownSkillError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(lore) = s.strip_suffix(" Lore") {
+            return Ok(Skill::Lore(lore.into()));
+        }
+        Skill::values_excluding_lore()
+            .iter()
+            .find(|x| x.to_string() == s)
+            .cloned()
+            .ok_or_else(|| UnknownSkillError(s.to_string()))
+    }
+}
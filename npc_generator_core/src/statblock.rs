@@ -4,7 +4,7 @@ use std::{borrow::Cow, sync::Arc};
 
 use crate::{Ability, AgeRange, Ancestry, Heritage, Proficiencies, Skill, Trait};
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct NpcFlavor {
     pub description_line: String,
     pub hair_and_eyes_line: String,
@@ -192,7 +192,7 @@ impl Statblock {
         PF2eStats(self.clone())
     }
 
-    pub fn name(&self) -> std::borrow::Cow<str> {
+    pub fn name(&self) -> std::borrow::Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
 
@@ -238,6 +238,22 @@ impl Statblock {
         self.will_save
     }
 
+    pub fn armor_class(&self) -> i16 {
+        self.armor_class
+    }
+
+    pub fn hit_points(&self) -> i32 {
+        self.hit_points
+    }
+
+    pub fn land_speed(&self) -> u16 {
+        self.land_speed
+    }
+
+    pub fn items(&self) -> &[StatblockString] {
+        &self.items
+    }
+
     pub fn sex(&self) -> &str {
         &self.sex
     }
@@ -341,6 +357,10 @@ impl Statblock {
     pub fn heritage(&self) -> Option<&Heritage> {
         self.heritage.as_ref()
     }
+
+    pub fn proficiencies(&self) -> &Proficiencies {
+        &self.proficiencies
+    }
 }
 
 impl Default for Statblock {
@@ -450,6 +470,12 @@ impl PF2eStats {
     }
 }
 
+impl From<PF2eStats> for Statblock {
+    fn from(value: PF2eStats) -> Self {
+        value.0
+    }
+}
+
 impl Display for PF2eStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "{}", Self::start_codeblock())?;
@@ -30,8 +30,8 @@ impl AsRef<str> for Trait {
     }
 }
 
-impl Into<String> for Trait {
-    fn into(self) -> String {
-        self.0.to_string()
+impl From<Trait> for String {
+    fn from(value: Trait) -> Self {
+        value.0.to_string()
     }
 }
@@ -31,7 +31,7 @@ impl WeightPreset {
         }
     }
 
-    pub fn name(&self) -> Cow<str> {
+    pub fn name(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.name)
     }
 